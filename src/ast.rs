@@ -0,0 +1,311 @@
+use crate::parser::is_reserved;
+use std::fmt;
+
+/// A parsed SQL statement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Select(Select),
+    Insert(Insert),
+    Update(Update),
+    Delete(Delete),
+    CreateTable(CreateTable),
+    AlterTable(AlterTable),
+    DropTable { name: String, if_exists: bool },
+    DropIndex { name: String, if_exists: bool },
+    Begin,
+    Commit,
+    Rollback,
+    Savepoint(String),
+    Release(String),
+    RollbackTo(String),
+    Pragma { name: String, value: Option<Literal> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    pub columns: Vec<ResultColumn>,
+    pub from: Option<String>,
+    pub where_clause: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResultColumn {
+    Star,
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Insert {
+    pub table: String,
+    /// Empty when the statement lists no columns, meaning all of them in order.
+    pub columns: Vec<String>,
+    pub values: Vec<Vec<Expr>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    pub table: String,
+    pub assignments: Vec<(String, Expr)>,
+    pub where_clause: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delete {
+    pub table: String,
+    pub where_clause: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTable {
+    pub name: String,
+    pub if_not_exists: bool,
+    pub columns: Vec<ColumnDef>,
+}
+
+/// `ALTER TABLE table action`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterTable {
+    pub table: String,
+    pub action: AlterAction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterAction {
+    AddColumn(ColumnDef),
+    RenameColumn { from: String, to: String },
+    RenameTable(String),
+    DropColumn(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDef {
+    pub name: String,
+    pub type_name: Option<String>,
+    pub constraints: Vec<ColumnConstraint>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnConstraint {
+    PrimaryKey { autoincrement: bool },
+    NotNull,
+    Unique,
+    Default(Expr),
+    Check(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Literal),
+    Column(String),
+    /// `?`, `?NNN` or `:name`, by its index counting from 1. The value bound
+    /// to it takes its place before the statement runs.
+    Parameter(usize),
+    Unary { op: UnaryOp, expr: Box<Expr> },
+    Binary { op: BinaryOp, left: Box<Expr>, right: Box<Expr> },
+    /// `expr [NOT] IN (list...)`
+    In { expr: Box<Expr>, list: Vec<Expr>, negated: bool },
+    /// `expr [NOT] BETWEEN low AND high`
+    Between { expr: Box<Expr>, low: Box<Expr>, high: Box<Expr>, negated: bool },
+    /// `expr IS [NOT] NULL`
+    IsNull { expr: Box<Expr>, negated: bool },
+    /// `name(args...)`
+    Function { name: String, args: Vec<Expr> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Integer(i64),
+    Real(f64),
+    String(String),
+    Blob(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Not,
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Concat,
+}
+
+// Printing a statement gives back SQL that parses to the same AST, which is
+// how table definitions are stored in the database.
+
+impl fmt::Display for CreateTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CREATE TABLE ")?;
+        if self.if_not_exists {
+            write!(f, "IF NOT EXISTS ")?;
+        }
+        write!(f, "{} (", Ident(&self.name))?;
+        for (i, column) in self.columns.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{column}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for ColumnDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Ident(&self.name))?;
+        if let Some(type_name) = &self.type_name {
+            write!(f, " {type_name}")?;
+        }
+        for constraint in &self.constraints {
+            write!(f, " {constraint}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ColumnConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnConstraint::PrimaryKey { autoincrement: false } => write!(f, "PRIMARY KEY"),
+            ColumnConstraint::PrimaryKey { autoincrement: true } => write!(f, "PRIMARY KEY AUTOINCREMENT"),
+            ColumnConstraint::NotNull => write!(f, "NOT NULL"),
+            ColumnConstraint::Unique => write!(f, "UNIQUE"),
+            ColumnConstraint::Default(expr) => write!(f, "DEFAULT ({expr})"),
+            ColumnConstraint::Check(expr) => write!(f, "CHECK ({expr})"),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let not = |negated: bool| if negated { "NOT " } else { "" };
+        match self {
+            Expr::Literal(literal) => write!(f, "{literal}"),
+            Expr::Column(name) => write!(f, "{}", Ident(name)),
+            Expr::Parameter(index) => write!(f, "?{index}"),
+            Expr::Unary { op: UnaryOp::Not, expr } => write!(f, "NOT {}", Operand(expr)),
+            Expr::Unary { op: UnaryOp::Negate, expr } => write!(f, "-{}", Operand(expr)),
+            Expr::Binary { op, left, right } => write!(f, "{} {op} {}", Operand(left), Operand(right)),
+            Expr::In { expr, list, negated } => {
+                write!(f, "{} {}IN (", Operand(expr), not(*negated))?;
+                for (i, item) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, ")")
+            }
+            Expr::Between { expr, low, high, negated } => write!(
+                f,
+                "{} {}BETWEEN {} AND {}",
+                Operand(expr),
+                not(*negated),
+                Operand(low),
+                Operand(high)
+            ),
+            Expr::IsNull { expr, negated } => write!(f, "{} IS {}NULL", Operand(expr), not(*negated)),
+            Expr::Function { name, args } => {
+                write!(f, "{}(", Ident(name))?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Null => write!(f, "NULL"),
+            Literal::Integer(n) => write!(f, "{n}"),
+            // No literal overflows to infinity, so it is written as the
+            // product that does; NaN is NULL wherever it comes up.
+            Literal::Real(n) if n.is_nan() => write!(f, "NULL"),
+            Literal::Real(n) if n.is_infinite() => write!(f, "({}1e308 * 10)", if *n < 0.0 { "-" } else { "" }),
+            Literal::Real(n) => write!(f, "{n:?}"),
+            Literal::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            Literal::Blob(bytes) => {
+                write!(f, "X'")?;
+                for byte in bytes {
+                    write!(f, "{byte:02X}")?;
+                }
+                write!(f, "'")
+            }
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            BinaryOp::Eq => "=",
+            BinaryOp::NotEq => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::And => "AND",
+            BinaryOp::Or => "OR",
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Concat => "||",
+        };
+        write!(f, "{op}")
+    }
+}
+
+/// An operand of a larger expression, parenthesized unless it is atomic.
+struct Operand<'a>(&'a Expr);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Expr::Literal(Literal::Integer(n)) if *n < 0 => write!(f, "({n})"),
+            Expr::Literal(literal @ Literal::Real(n)) if *n < 0.0 => write!(f, "({literal})"),
+            expr @ (Expr::Literal(_) | Expr::Column(_) | Expr::Parameter(_) | Expr::Function { .. }) => {
+                write!(f, "{expr}")
+            }
+            expr => write!(f, "({expr})"),
+        }
+    }
+}
+
+/// An identifier, quoted when it isn't a plain word.
+pub struct Ident<'a>(pub &'a str);
+
+impl fmt::Display for Ident<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.0;
+        let plain = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            && !is_reserved(name);
+        if plain {
+            return write!(f, "{name}");
+        }
+        write!(f, "\"{}\"", name.replace('"', "\"\""))
+    }
+}
@@ -0,0 +1,518 @@
+use crate::ast::{self, Expr, Literal, ResultColumn, Select};
+use crate::cursor::Cursor;
+use crate::database::Database;
+use crate::error::{Error, Object, Result};
+use crate::parser;
+use crate::persistence::{PagerConfig, PagerStats};
+use crate::statement;
+use crate::value::{FromValue, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::vec;
+
+/// A connection to a database file, through which SQL is run.
+///
+/// Outside of a transaction every statement is written to disk as soon as
+/// it succeeds, and one that fails leaves nothing behind. Between `BEGIN`
+/// and `COMMIT` changes are held back until the commit.
+///
+/// The file is opened, and created if need be, when the connection is
+/// made. An encrypted file opened without a key waits for `PRAGMA key`.
+pub struct Connection {
+    path: PathBuf,
+    config: PagerConfig,
+    db: Option<Database>,
+    /// Set while the file is one this connection created and has yet to
+    /// run a statement against, so that `PRAGMA key` can create it again
+    /// encrypted.
+    created: bool,
+    last_insert_rowid: i64,
+    changes: usize,
+}
+
+/// A statement parsed once, to be run any number of times with different
+/// values bound to its parameters, from [`Connection::prepare`].
+///
+/// Parameters are written `?`, `?NNN` or `:name`, and numbered from 1: a
+/// bare `?` takes the number after the highest so far, and a name used
+/// twice is one parameter. Those left unbound are NULL.
+///
+/// Each run starts with [`Statement::step`]. A query returns its rows one
+/// per step, then `None`; any other statement runs on the first step.
+/// Once it's started, [`Statement::reset`] readies it to run again, with
+/// its values still bound.
+pub struct Statement<'conn> {
+    conn: &'conn mut Connection,
+    statement: ast::Statement,
+    /// The name of each parameter, `None` for a bare `?`.
+    parameters: Vec<Option<String>>,
+    values: Vec<Value>,
+    columns: Vec<String>,
+    /// The rows the current run has yet to return, `None` until it starts.
+    rows: Option<vec::IntoIter<Row>>,
+}
+
+/// The rows a query returned, along with the names of its columns.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rows {
+    columns: Vec<String>,
+    rows: Vec<Row>,
+    rowid_lookup: bool,
+}
+
+/// One row of a query's result, with a value per column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    values: Vec<Value>,
+}
+
+impl Connection {
+    /// Connects to the database at `path` with the default settings.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with(path, &PagerConfig::default())
+    }
+
+    /// Connects to the database at `path`. The page size and compression
+    /// only apply if the file is created.
+    pub fn open_with(path: impl AsRef<Path>, config: &PagerConfig) -> Result<Self> {
+        let path = path.as_ref();
+        let created = fs::metadata(path).map_or(true, |metadata| metadata.len() == 0);
+        let db = match Database::open_with(path, config) {
+            Ok(db) => Some(db),
+            Err(_) if config.key.is_none() && Database::is_encrypted(path) => None,
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            config: config.clone(),
+            db,
+            created,
+            last_insert_rowid: 0,
+            changes: 0,
+        })
+    }
+
+    /// Parses one statement to run through the returned [`Statement`].
+    pub fn prepare(&mut self, sql: &str) -> Result<Statement<'_>> {
+        let (statement, parameters) = parser::parse_with_parameters(sql)?;
+        Ok(Statement {
+            conn: self,
+            statement,
+            values: vec![Value::Null; parameters.len()],
+            parameters,
+            columns: vec![],
+            rows: None,
+        })
+    }
+
+    /// Runs one statement that doesn't return rows, and returns how many
+    /// rows it inserted, updated or deleted: `0` for other statements.
+    /// Queries go through [`Connection::query`].
+    pub fn execute(&mut self, sql: &str) -> Result<usize> {
+        match parser::parse(sql)? {
+            ast::Statement::Select(_) => Err(Error::misuse("A SELECT returns rows; run it with query.")),
+            ast::Statement::Pragma { name, value } => self.pragma(&name, value).map(|()| 0),
+            statement => self.write(statement),
+        }
+    }
+
+    /// Runs a `SELECT` and returns the rows it matches.
+    pub fn query(&mut self, sql: &str) -> Result<Rows> {
+        match parser::parse(sql)? {
+            ast::Statement::Select(select) => self.select(&select),
+            _ => Err(Error::misuse("Only a SELECT returns rows; run other statements with execute.")),
+        }
+    }
+
+    /// The rowid of the last row inserted through this connection, `0`
+    /// before the first.
+    pub fn last_insert_rowid(&self) -> i64 {
+        self.last_insert_rowid
+    }
+
+    /// How many rows the last insert, update or delete changed.
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    /// Whether a transaction is open, holding changes back from the file.
+    pub fn in_transaction(&self) -> bool {
+        self.db.as_ref().is_some_and(Database::in_transaction)
+    }
+
+    /// Figures about the file: its pages, compression and encryption.
+    pub fn stats(&mut self) -> Result<PagerStats> {
+        Ok(self.db()?.pager.stats())
+    }
+
+    /// Closes the file, rolling back a transaction that is still open. The
+    /// connection can go on being used, and opens the file again when it
+    /// needs it.
+    pub fn close(&mut self) -> Result<()> {
+        match self.db.take() {
+            Some(mut db) => db.db_close(),
+            None => Ok(()),
+        }
+    }
+
+    fn db(&mut self) -> Result<&mut Database> {
+        if self.db.is_none() {
+            self.db = Some(Database::open_with(&self.path, &self.config)?);
+        }
+        Ok(self.db.as_mut().unwrap())
+    }
+
+    fn select(&mut self, select: &Select) -> Result<Rows> {
+        let Some(name) = &select.from else {
+            let values = statement::select_without_table(select, self.last_insert_rowid)?;
+            let columns = select
+                .columns
+                .iter()
+                .map(|column| match column {
+                    ResultColumn::Star => String::from("*"),
+                    ResultColumn::Expr(expr) => expr.to_string(),
+                })
+                .collect();
+            let rows = match values.is_empty() {
+                true => vec![],
+                false => vec![Row { values }],
+            };
+            return Ok(Rows {
+                columns,
+                rows,
+                rowid_lookup: false,
+            });
+        };
+        let mut table = self.db()?.table(name)?;
+        let rows = statement::select(&mut Cursor::new(&mut table), select)?;
+        let rows = rows
+            .iter()
+            .map(|row| statement::result_row(select, &table.schema, row).map(|values| Row { values }))
+            .collect::<Result<_>>()?;
+        Ok(Rows {
+            columns: statement::result_columns(select, &table.schema),
+            rows,
+            rowid_lookup: statement::looks_up_rowids(select, &table.schema),
+        })
+    }
+
+    /// Runs a statement that changes the database or its transaction.
+    fn write(&mut self, statement: ast::Statement) -> Result<usize> {
+        let mut last_insert_rowid = self.last_insert_rowid;
+        self.created = false;
+        let db = self.db()?;
+        // A statement that fails partway mustn't leave half its changes in
+        // the transaction. Those that start or end a transaction or a
+        // savepoint are left out, as they change the savepoints themselves.
+        let result = match is_transaction_control(&statement) {
+            true => apply(statement, db, &mut last_insert_rowid),
+            false => db.atomically(|db| apply(statement, db, &mut last_insert_rowid)),
+        };
+        // Outside of a transaction a failed statement mustn't leave changes
+        // behind for the next one to write out.
+        if !db.in_transaction() {
+            match result {
+                Ok(_) => db.commit()?,
+                Err(_) => db.discard_changes()?,
+            }
+        }
+        self.last_insert_rowid = last_insert_rowid;
+        if let Ok(Some(count)) = result {
+            self.changes = count;
+        }
+        result.map(Option::unwrap_or_default)
+    }
+
+    /// Sets a pragma. The file is opened again with a new key, so only
+    /// outside of a transaction. A file this connection has just created
+    /// is created again, encrypted with the key.
+    fn pragma(&mut self, name: &str, value: Option<Literal>) -> Result<()> {
+        match (name, value) {
+            ("key", Some(Literal::String(key))) if !key.is_empty() => {
+                if self.in_transaction() {
+                    return Err(Error::misuse("Cannot change the key within a transaction."));
+                }
+                self.close()?;
+                if self.created {
+                    fs::remove_file(&self.path).map_err(Error::io(format!("remove {}", self.path.display())))?;
+                }
+                let config = PagerConfig {
+                    key: Some(key),
+                    ..self.config.clone()
+                };
+                self.db = Some(Database::open_with(&self.path, &config)?);
+                self.config = config;
+                Ok(())
+            }
+            ("key", _) => Err(Error::misuse("Expected PRAGMA key = '<key>'.")),
+            (other, _) => Err(Error::misuse(format!("Unknown pragma: {other}."))),
+        }
+    }
+}
+
+/// Runs `statement` against `db` and returns how many rows it changed, or
+/// `None` if it doesn't change rows.
+fn apply(statement: ast::Statement, db: &mut Database, last_insert_rowid: &mut i64) -> Result<Option<usize>> {
+    let none = |()| None;
+    match statement {
+        ast::Statement::Insert(insert) => {
+            let mut table = db.writable_table(&insert.table)?;
+            *last_insert_rowid = statement::insert(&mut Cursor::new(&mut table), &insert)?;
+            Ok(Some(insert.values.len()))
+        }
+        ast::Statement::Update(update) => {
+            let mut table = db.writable_table(&update.table)?;
+            statement::update(&mut Cursor::new(&mut table), &update).map(Some)
+        }
+        ast::Statement::Delete(delete) => {
+            let mut table = db.writable_table(&delete.table)?;
+            statement::delete(&mut Cursor::new(&mut table), &delete).map(Some)
+        }
+        ast::Statement::CreateTable(create) => db.create_table(&create).map(none),
+        ast::Statement::AlterTable(alter) => db.alter_table(&alter).map(none),
+        ast::Statement::DropTable { name, if_exists } => db.drop_table(&name, if_exists).map(none),
+        ast::Statement::DropIndex { name, if_exists } => db.drop_index(&name, if_exists).map(none),
+        ast::Statement::Begin => db.begin().map(none),
+        ast::Statement::Commit => db.commit_transaction().map(none),
+        ast::Statement::Rollback => db.rollback().map(none),
+        ast::Statement::Savepoint(name) => {
+            db.savepoint(&name);
+            Ok(None)
+        }
+        ast::Statement::Release(name) => db.release(&name).map(none),
+        ast::Statement::RollbackTo(name) => db.rollback_to(&name).map(none),
+        ast::Statement::Select(_) | ast::Statement::Pragma { .. } => unreachable!("handled by the connection"),
+    }
+}
+
+fn is_transaction_control(statement: &ast::Statement) -> bool {
+    matches!(
+        statement,
+        ast::Statement::Begin
+            | ast::Statement::Commit
+            | ast::Statement::Rollback
+            | ast::Statement::Savepoint(_)
+            | ast::Statement::Release(_)
+            | ast::Statement::RollbackTo(_)
+    )
+}
+
+impl Statement<'_> {
+    /// How many parameters the statement has: the highest index of one.
+    pub fn parameter_count(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// The index of the parameter written as `name`, such as `:id` or `?2`.
+    pub fn parameter_index(&self, name: &str) -> Option<usize> {
+        let position = self.parameters.iter().position(|known| known.as_deref() == Some(name))?;
+        Some(position + 1)
+    }
+
+    /// Binds NULL to the parameter numbered `index`, as if left unbound.
+    pub fn bind_null(&mut self, index: usize) -> Result<()> {
+        self.bind_value(index, Value::Null)
+    }
+
+    /// Binds an integer to the parameter numbered `index`.
+    pub fn bind_int(&mut self, index: usize, value: i64) -> Result<()> {
+        self.bind_value(index, Value::Integer(value))
+    }
+
+    /// Binds a real to the parameter numbered `index`. NaN binds NULL, as
+    /// in SQLite.
+    pub fn bind_real(&mut self, index: usize, value: f64) -> Result<()> {
+        self.bind_value(index, Value::Real(value))
+    }
+
+    /// Binds a copy of `value` as text to the parameter numbered `index`.
+    pub fn bind_text(&mut self, index: usize, value: &str) -> Result<()> {
+        self.bind_value(index, Value::Text(value.to_string()))
+    }
+
+    /// Binds a copy of `value` as a blob to the parameter numbered `index`.
+    pub fn bind_blob(&mut self, index: usize, value: &[u8]) -> Result<()> {
+        self.bind_value(index, Value::Blob(value.to_vec()))
+    }
+
+    /// Binds `value` to the parameter numbered `index`, counting from 1.
+    /// A run that has started must be reset first. A real NaN binds NULL.
+    pub fn bind_value(&mut self, index: usize, value: Value) -> Result<()> {
+        if self.rows.is_some() {
+            return Err(Error::misuse("Reset the statement before binding new values."));
+        }
+        let slot = index
+            .checked_sub(1)
+            .and_then(|position| self.values.get_mut(position))
+            .ok_or_else(|| Error::not_found(Object::Parameter, index.to_string()))?;
+        *slot = match value {
+            Value::Real(n) if n.is_nan() => Value::Null,
+            value => value,
+        };
+        Ok(())
+    }
+
+    /// Sets every parameter back to NULL.
+    pub fn clear_bindings(&mut self) -> Result<()> {
+        (1..=self.values.len()).try_for_each(|index| self.bind_null(index))
+    }
+
+    /// Runs the statement if it hasn't started, and returns the next row of
+    /// a query, or `None` once there are no more.
+    pub fn step(&mut self) -> Result<Option<Row>> {
+        if self.rows.is_none() {
+            let mut statement = self.statement.clone();
+            bind_parameters(&mut statement, &self.values);
+            let rows = match statement {
+                ast::Statement::Select(select) => self.conn.select(&select)?,
+                ast::Statement::Pragma { name, value } => {
+                    self.conn.pragma(&name, value)?;
+                    Rows::default()
+                }
+                statement => {
+                    self.conn.write(statement)?;
+                    Rows::default()
+                }
+            };
+            self.columns = rows.columns;
+            self.rows = Some(rows.rows.into_iter());
+        }
+        Ok(self.rows.as_mut().and_then(Iterator::next))
+    }
+
+    /// Readies the statement to run again from the start, dropping the rows
+    /// it has yet to return. The values bound to it stay.
+    pub fn reset(&mut self) {
+        self.rows = None;
+    }
+
+    /// The names of the columns of a query's rows, known once it's run.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+}
+
+/// Puts the values bound to the parameters of `statement` in their place.
+/// Only the statements that read or change rows can have parameters.
+fn bind_parameters(statement: &mut ast::Statement, values: &[Value]) {
+    let exprs: Vec<&mut Expr> = match statement {
+        ast::Statement::Select(select) => select
+            .columns
+            .iter_mut()
+            .filter_map(|column| match column {
+                ResultColumn::Expr(expr) => Some(expr),
+                ResultColumn::Star => None,
+            })
+            .chain(&mut select.where_clause)
+            .collect(),
+        ast::Statement::Insert(insert) => insert.values.iter_mut().flatten().collect(),
+        ast::Statement::Update(update) => update
+            .assignments
+            .iter_mut()
+            .map(|(_, expr)| expr)
+            .chain(&mut update.where_clause)
+            .collect(),
+        ast::Statement::Delete(delete) => delete.where_clause.iter_mut().collect(),
+        _ => vec![],
+    };
+    exprs.into_iter().for_each(|expr| bind_expr(expr, values));
+}
+
+fn bind_expr(expr: &mut Expr, values: &[Value]) {
+    match expr {
+        Expr::Parameter(index) => *expr = Expr::Literal(Literal::from(&values[*index - 1])),
+        Expr::Literal(_) | Expr::Column(_) => {}
+        Expr::Unary { expr, .. } | Expr::IsNull { expr, .. } => bind_expr(expr, values),
+        Expr::Binary { left, right, .. } => {
+            bind_expr(left, values);
+            bind_expr(right, values);
+        }
+        Expr::In { expr, list, .. } => {
+            bind_expr(expr, values);
+            list.iter_mut().for_each(|item| bind_expr(item, values));
+        }
+        Expr::Between { expr, low, high, .. } => {
+            for expr in [expr, low, high] {
+                bind_expr(expr, values);
+            }
+        }
+        Expr::Function { args, .. } => args.iter_mut().for_each(|arg| bind_expr(arg, values)),
+    }
+}
+
+impl Rows {
+    /// The names of the columns, in the order of each row's values.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Whether the query asked for rows by their rowids, as `WHERE id = 2`
+    /// does, so that no rows means no row has those rowids.
+    pub fn is_rowid_lookup(&self) -> bool {
+        self.rowid_lookup
+    }
+
+    /// How many rows there are.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether the query matched no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The rows in the order the query returned them.
+    pub fn iter(&self) -> slice::Iter<'_, Row> {
+        self.rows.iter()
+    }
+}
+
+impl IntoIterator for Rows {
+    type Item = Row;
+    type IntoIter = vec::IntoIter<Row>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Rows {
+    type Item = &'a Row;
+    type IntoIter = slice::Iter<'a, Row>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.iter()
+    }
+}
+
+impl Row {
+    /// The value of the `index`-th column, counting from 0.
+    pub fn value(&self, index: usize) -> Option<&Value> {
+        self.values.get(index)
+    }
+
+    /// The value of the `index`-th column as a `T`, such as an `i64` or an
+    /// `Option<String>` for a column that may be NULL.
+    pub fn get<T: FromValue>(&self, index: usize) -> Result<T> {
+        let value = self
+            .value(index)
+            .ok_or_else(|| Error::not_found(Object::Column, index.to_string()))?;
+        T::from_value(value).ok_or(Error::Mismatch {
+            index,
+            expected: T::KIND,
+            found: value.kind(),
+        })
+    }
+
+    /// The row's values, one per column.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Takes the row's values, one per column.
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+}
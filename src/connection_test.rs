@@ -0,0 +1,306 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::connection::Connection;
+    use crate::error::{Constraint, Error, Object, Result};
+    use crate::persistence::PagerConfig;
+    use crate::schema::USERS_SCHEMA;
+    use crate::value::Value;
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    #[test]
+    fn execute_and_query_round_trip_through_the_file() {
+        let test_database_name = "test_db_connection";
+        let mut conn = Connection::open(test_database_name).unwrap();
+        assert!(conn.query("SELECT * FROM rustqlite_schema").unwrap().is_empty());
+        conn.execute("CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT NOT NULL)").unwrap();
+        assert_eq!(conn.execute("INSERT INTO people (name) VALUES ('alice'), ('bob')").unwrap(), 2);
+        assert_eq!(conn.last_insert_rowid(), 2);
+        assert_eq!(conn.execute("UPDATE people SET name = 'carol' WHERE id = 2").unwrap(), 1);
+        conn.close().unwrap();
+
+        let mut conn = Connection::open(test_database_name).unwrap();
+        let rows = conn.query("SELECT * FROM people").unwrap();
+        assert_eq!(rows.columns(), ["id", "name"]);
+        let values: Vec<Vec<Value>> = rows.into_iter().map(|row| row.into_values()).collect();
+        assert_eq!(values, [
+            vec![Value::Integer(1), text("alice")],
+            vec![Value::Integer(2), text("carol")],
+        ]);
+        let rows = conn.query("SELECT name, id * 10, *, upper FROM people WHERE id = 2");
+        assert_eq!(rows, Err(Error::not_found(Object::Column, "upper")));
+        let rows = conn.query("SELECT name, id * 10, * FROM people WHERE id = 2").unwrap();
+        assert_eq!(rows.columns(), ["name", "id * 10", "id", "name"]);
+        assert_eq!(rows.iter().next().unwrap().values(), [text("carol"), Value::Integer(20), Value::Integer(2), text("carol")]);
+        assert_eq!(conn.execute("DELETE FROM people").unwrap(), 2);
+        assert_eq!(conn.query("SELECT missing FROM people"), Err(Error::not_found(Object::Column, "missing")));
+        assert!(!conn.query("SELECT * FROM people").unwrap().is_rowid_lookup());
+        let rows = conn.query("SELECT * FROM people WHERE id = 2").unwrap();
+        assert!(rows.is_empty() && rows.is_rowid_lookup());
+
+        let rows = conn.query("SELECT 1 + 2, 'x'").unwrap();
+        assert_eq!(rows.columns(), ["1 + 2", "'x'"]);
+        assert_eq!(rows.iter().next().unwrap().value(0), Some(&Value::Integer(3)));
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn statements_go_through_the_method_that_fits_them() {
+        let test_database_name = "test_db_connection_misuse";
+        let mut conn = Connection::open(test_database_name).unwrap();
+        assert!(matches!(conn.execute("SELECT * FROM users"), Err(Error::Misuse(_))));
+        assert!(matches!(conn.query("DELETE FROM users"), Err(Error::Misuse(_))));
+        assert!(matches!(conn.execute("INSERT INTO"), Err(Error::Syntax(_))));
+        assert_eq!(conn.query("SELECT * FROM nobody"), Err(Error::not_found(Object::Table, "nobody")));
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn a_failed_statement_leaves_nothing_behind_outside_of_a_transaction() {
+        let test_database_name = "test_db_connection_failure";
+        let mut conn = Connection::open(test_database_name).unwrap();
+        conn.execute(USERS_SCHEMA).unwrap();
+        let err = conn.execute("INSERT INTO users VALUES (1, 'alice', 'a@example.com'), (1, 'bob', 'b@example.com')");
+        assert_eq!(err, Err(Error::Constraint(Constraint::Unique { column: String::from("id") })));
+        assert!(conn.query("SELECT * FROM users").unwrap().is_empty());
+
+        conn.execute("BEGIN").unwrap();
+        assert!(conn.in_transaction());
+        conn.execute("INSERT INTO users VALUES (2, 'bob', 'b@example.com')").unwrap();
+        assert!(matches!(conn.execute("PRAGMA key = 'secret'"), Err(Error::Misuse(_))));
+        // Closing rolls back the open transaction.
+        conn.close().unwrap();
+        assert!(!conn.in_transaction());
+        assert!(conn.query("SELECT * FROM users").unwrap().is_empty());
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn a_failed_statement_inside_a_transaction_is_undone_on_its_own() {
+        let test_database_name = "test_db_connection_statement";
+        let mut conn = Connection::open(test_database_name).unwrap();
+        conn.execute("CREATE TABLE t (id INTEGER PRIMARY KEY, u TEXT UNIQUE, n INTEGER CHECK (n < 10))").unwrap();
+        conn.execute("BEGIN").unwrap();
+        conn.execute("INSERT INTO t (u, n) VALUES ('a', 1)").unwrap();
+        let err = conn.execute("INSERT INTO t (u, n) VALUES ('b', 1), ('a', 1)");
+        assert_eq!(err, Err(Error::Constraint(Constraint::Unique { column: String::from("u") })));
+        conn.execute("INSERT INTO t (u, n) VALUES ('c', 2)").unwrap();
+        // The first row passes its CHECK, the second doesn't.
+        assert!(matches!(conn.execute("UPDATE t SET n = n * 6"), Err(Error::Constraint(Constraint::Check { .. }))));
+        assert!(conn.in_transaction());
+        conn.execute("COMMIT").unwrap();
+        conn.close().unwrap();
+
+        let mut conn = Connection::open(test_database_name).unwrap();
+        let rows: Vec<Vec<Value>> = conn.query("SELECT * FROM t").unwrap().into_iter().map(|row| row.into_values()).collect();
+        assert_eq!(rows, [
+            vec![Value::Integer(1), text("a"), Value::Integer(1)],
+            vec![Value::Integer(2), text("c"), Value::Integer(2)],
+        ]);
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn an_infinite_default_survives_reopening_the_file() {
+        let test_database_name = "test_db_connection_infinity";
+        let mut conn = Connection::open(test_database_name).unwrap();
+        assert!(matches!(conn.execute("CREATE TABLE t (a REAL DEFAULT 1e400)"), Err(Error::Syntax(_))));
+        conn.execute("CREATE TABLE t (a REAL DEFAULT (1e308 * 10), b INTEGER UNIQUE)").unwrap();
+        conn.execute("INSERT INTO t (b) VALUES (1)").unwrap();
+        // A failed statement reloads the catalog.
+        assert!(matches!(conn.execute("INSERT INTO t (b) VALUES (1)"), Err(Error::Constraint(_))));
+        conn.close().unwrap();
+
+        let mut conn = Connection::open(test_database_name).unwrap();
+        let rows = conn.query("SELECT a FROM t").unwrap();
+        assert_eq!(rows.iter().next().unwrap().values(), [Value::Real(f64::INFINITY)]);
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn pragma_key_opens_an_encrypted_database() {
+        let test_database_name = "test_db_connection_key";
+        let config = PagerConfig {
+            key: Some(String::from("correct horse")),
+            ..PagerConfig::default()
+        };
+        let mut conn = Connection::open_with(test_database_name, &config).unwrap();
+        conn.execute(USERS_SCHEMA).unwrap();
+        conn.execute("INSERT INTO users VALUES (7, 'grace', 'grace@example.com')").unwrap();
+        assert!(conn.stats().unwrap().encrypted);
+        conn.close().unwrap();
+
+        let wrong = PagerConfig {
+            key: Some(String::from("battery staple")),
+            ..PagerConfig::default()
+        };
+        assert!(matches!(Connection::open_with(test_database_name, &wrong), Err(Error::Io { .. })));
+
+        let mut conn = Connection::open(test_database_name).unwrap();
+        assert!(matches!(conn.query("SELECT * FROM users"), Err(Error::Io { .. })));
+        assert!(matches!(conn.execute("PRAGMA key = 'battery staple'"), Err(Error::Io { .. })));
+        conn.execute("PRAGMA key = 'correct horse'").unwrap();
+        assert_eq!(conn.query("SELECT * FROM users WHERE id = 7").unwrap().len(), 1);
+        assert!(matches!(conn.execute("PRAGMA cache_size = 10"), Err(Error::Misuse(_))));
+        conn.close().unwrap();
+
+        // A file the connection has just created is created again, encrypted.
+        fs::remove_file(test_database_name).unwrap();
+        let mut conn = Connection::open(test_database_name).unwrap();
+        conn.execute("PRAGMA key = 'correct horse'").unwrap();
+        conn.execute(USERS_SCHEMA).unwrap();
+        assert!(conn.stats().unwrap().encrypted);
+        conn.execute("INSERT INTO users VALUES (8, 'ada', 'ada@example.com')").unwrap();
+        assert!(matches!(conn.execute("PRAGMA key = 'battery staple'"), Err(Error::Io { .. })));
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn opening_fails_on_a_file_that_cannot_be_used() {
+        let test_database_name = "test_db_connection_open";
+        let config = PagerConfig {
+            page_size: 1000,
+            ..PagerConfig::default()
+        };
+        assert!(matches!(Connection::open_with(test_database_name, &config), Err(Error::Io { .. })));
+        assert!(matches!(Connection::open("test_db_missing/db"), Err(Error::Io { .. })));
+
+        fs::write(test_database_name, "not a database, just some text that is long enough to fill a header ".repeat(2)).unwrap();
+        assert!(matches!(Connection::open(test_database_name), Err(Error::Io { .. })));
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn prepared_statements_run_again_with_new_values() {
+        let test_database_name = "test_db_connection_prepared";
+        let mut conn = Connection::open(test_database_name).unwrap();
+        conn.execute(USERS_SCHEMA).unwrap();
+        let mut insert = conn.prepare("INSERT INTO users (name, email) VALUES (:name, ?)").unwrap();
+        assert_eq!(insert.parameter_count(), 2);
+        assert_eq!(insert.parameter_index(":name"), Some(1));
+        assert_eq!(insert.parameter_index(":email"), None);
+        for name in ["alice", "o'brien"] {
+            insert.bind_text(1, name).unwrap();
+            insert.bind_text(2, &format!("{name}@example.com")).unwrap();
+            assert_eq!(insert.step(), Ok(None));
+            assert!(matches!(insert.bind_null(1), Err(Error::Misuse(_))));
+            insert.reset();
+        }
+        insert.clear_bindings().unwrap();
+        assert_eq!(insert.bind_int(3, 1), Err(Error::not_found(Object::Parameter, "3")));
+        assert_eq!(insert.step(), Ok(None));
+        drop(insert);
+        assert_eq!(conn.last_insert_rowid(), 3);
+        assert_eq!(conn.changes(), 1);
+
+        let mut select = conn.prepare("SELECT * FROM users WHERE name = ? OR id IN (?2, ?3)").unwrap();
+        select.bind_text(1, "o'brien").unwrap();
+        select.bind_int(2, 3).unwrap();
+        let mut ids = vec![];
+        while let Some(row) = select.step().unwrap() {
+            ids.push(row.get::<i64>(0).unwrap());
+        }
+        assert_eq!(ids, [2, 3]);
+        assert_eq!(select.columns(), ["id", "name", "email"]);
+        assert_eq!(select.step(), Ok(None));
+
+        // Values can't be mistaken for SQL.
+        select.reset();
+        select.bind_text(1, "x' OR 1 = 1 --").unwrap();
+        select.bind_null(2).unwrap();
+        assert_eq!(select.step(), Ok(None));
+        drop(select);
+
+        let mut update = conn.prepare("UPDATE users SET email = ?1 WHERE id = ?2").unwrap();
+        update.bind_blob(1, b"\x00\x01").unwrap();
+        update.bind_int(2, 1).unwrap();
+        update.step().unwrap();
+        drop(update);
+        assert_eq!(conn.changes(), 1);
+        let rows = conn.query("SELECT * FROM users WHERE id = 1").unwrap();
+        assert_eq!(rows.iter().next().unwrap().get::<Vec<u8>>(2), Ok(vec![0, 1]));
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn a_bound_nan_compares_as_null() {
+        let test_database_name = "test_db_connection_nan";
+        let mut conn = Connection::open(test_database_name).unwrap();
+        conn.execute(USERS_SCHEMA).unwrap();
+        conn.execute("INSERT INTO users VALUES (1, 'alice', 'a@example.com')").unwrap();
+        let mut select = conn.prepare("SELECT * FROM users WHERE id = ? OR id > ?1").unwrap();
+        select.bind_real(1, f64::NAN).unwrap();
+        assert_eq!(select.step(), Ok(None));
+        drop(select);
+
+        let mut select = conn.prepare("SELECT ? < 1, ?1 = ?1").unwrap();
+        select.bind_real(1, f64::NAN).unwrap();
+        assert_eq!(select.step().unwrap().unwrap().values(), [Value::Null, Value::Null]);
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn a_bound_nan_is_stored_as_null() {
+        let test_database_name = "test_db_connection_nan_stored";
+        let mut conn = Connection::open(test_database_name).unwrap();
+        conn.execute("CREATE TABLE readings (id INTEGER PRIMARY KEY, level REAL, strict REAL NOT NULL DEFAULT 0)")
+            .unwrap();
+        let mut insert = conn.prepare("INSERT INTO readings (id, level) VALUES (?, ?)").unwrap();
+        insert.bind_int(1, 1).unwrap();
+        insert.bind_real(2, f64::NAN).unwrap();
+        insert.step().unwrap();
+        drop(insert);
+        let mut insert = conn.prepare("INSERT INTO readings (id, strict) VALUES (?, ?)").unwrap();
+        insert.bind_int(1, 2).unwrap();
+        insert.bind_value(2, Value::Real(f64::NAN)).unwrap();
+        assert_eq!(
+            insert.step(),
+            Err(Error::Constraint(Constraint::NotNull { column: String::from("strict") }))
+        );
+        drop(insert);
+
+        let rows = conn.query("SELECT level FROM readings WHERE level IS NULL").unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows.iter().next().unwrap().values(), [Value::Null]);
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+
+    #[test]
+    fn typed_values_are_read_only_as_what_they_hold() {
+        let test_database_name = "test_db_connection_typed";
+        let mut conn = Connection::open(test_database_name).unwrap();
+        let mut select = conn.prepare("SELECT ?, ?, ?, ?").unwrap();
+        select.bind_int(1, 7).unwrap();
+        select.bind_real(2, 2.5).unwrap();
+        select.bind_text(3, "seven").unwrap();
+        let row = select.step().unwrap().unwrap();
+
+        assert_eq!(row.get::<i64>(0), Ok(7));
+        assert_eq!(row.get::<f64>(0), Ok(7.0));
+        assert_eq!(row.get::<bool>(0), Ok(true));
+        assert_eq!(row.get::<f64>(1), Ok(2.5));
+        assert_eq!(row.get::<String>(2), Ok(String::from("seven")));
+        assert_eq!(row.get::<Option<String>>(3), Ok(None));
+        assert_eq!(row.get::<Value>(3), Ok(Value::Null));
+        let mismatch: Result<i64> = row.get(2);
+        assert_eq!(mismatch, Err(Error::Mismatch { index: 2, expected: "an integer", found: "text" }));
+        assert_eq!(mismatch.unwrap_err().to_string(), "Column 2 holds text, not an integer.");
+        assert_eq!(row.get::<i64>(3).unwrap_err().to_string(), "Column 3 holds NULL, not an integer.");
+        assert_eq!(row.get::<i64>(4), Err(Error::not_found(Object::Column, "4")));
+
+        fs::remove_file(test_database_name).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::Sha256;
+use std::io;
+
+pub const SALT_SIZE: usize = 16;
+pub const NONCE_SIZE: usize = 12;
+pub const TAG_SIZE: usize = 16;
+/// A nonce and the tag of an empty message, enough to tell whether a key is right.
+pub const KEY_CHECK_SIZE: usize = NONCE_SIZE + TAG_SIZE;
+
+const KDF_ROUNDS: u32 = 100_000;
+const KEY_CHECK_AAD: &[u8] = b"rustqlite key check";
+
+/// Encrypts pages with ChaCha20-Poly1305. Every stored page is
+/// `[nonce][ciphertext][tag]` with a fresh random nonce, and the page number
+/// is authenticated too so pages can't be swapped around undetected.
+pub struct PageCipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl PageCipher {
+    /// Derives a cipher for a new database, returning it with the salt and
+    /// key check value to record in the header.
+    pub fn create(passphrase: &str) -> (Self, [u8; SALT_SIZE], [u8; KEY_CHECK_SIZE]) {
+        let mut salt = [0u8; SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+
+        let page_cipher = Self::derive(passphrase, &salt);
+        let mut key_check = [0u8; KEY_CHECK_SIZE];
+        key_check.copy_from_slice(&page_cipher.seal(KEY_CHECK_AAD, &[]));
+        (page_cipher, salt, key_check)
+    }
+
+    /// Derives the cipher for an existing database and checks it against the
+    /// header's key check value.
+    pub fn open(passphrase: &str, salt: &[u8; SALT_SIZE], key_check: &[u8; KEY_CHECK_SIZE]) -> io::Result<Self> {
+        let page_cipher = Self::derive(passphrase, salt);
+        page_cipher
+            .unseal(KEY_CHECK_AAD, key_check)
+            .map_err(|_| io::Error::new(io::ErrorKind::PermissionDenied, "wrong encryption key"))?;
+        Ok(page_cipher)
+    }
+
+    pub fn encrypt_page(&self, page_num: usize, data: &[u8]) -> Vec<u8> {
+        self.seal(&(page_num as u64).to_le_bytes(), data)
+    }
+
+    pub fn decrypt_page(&self, page_num: usize, data: &[u8]) -> io::Result<Vec<u8>> {
+        self.unseal(&(page_num as u64).to_le_bytes(), data)
+    }
+
+    fn derive(passphrase: &str, salt: &[u8; SALT_SIZE]) -> Self {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, KDF_ROUNDS, &mut key);
+        Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        }
+    }
+
+    fn seal(&self, aad: &[u8], msg: &[u8]) -> Vec<u8> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg, aad })
+            .expect("encrypting a page cannot fail");
+        let mut out = nonce.to_vec();
+        out.extend(ciphertext);
+        out
+    }
+
+    fn unseal(&self, aad: &[u8], data: &[u8]) -> io::Result<Vec<u8>> {
+        if data.len() < NONCE_SIZE + TAG_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "encrypted page too short"));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "page failed authentication"))
+    }
+}
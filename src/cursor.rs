@@ -1,38 +1,37 @@
-use crate::table::{Page, Table, ROWS_PER_PAGE, ROW_SIZE, TABLE_MAX_PAGES};
+use crate::error::Result;
+use crate::table::Table;
 
-pub struct Cursor<'a> {
-    pub(crate) table: &'a mut Table,
-    pub(crate) row_num: usize,
-    end_of_table: bool,
+/// A position among a table's rows: a cell of one of its leaf pages.
+pub struct Cursor<'a, 'db> {
+    pub(crate) table: &'a mut Table<'db>,
+    /// Index of the leaf in the table's directory.
+    pub(crate) leaf: usize,
+    /// Index of the cell within the leaf.
+    pub(crate) cell: usize,
+    pub(crate) end_of_table: bool,
 }
 
-impl<'a> Cursor<'a> {
-    pub fn new(table: &'a mut Table) -> Self {
-        let end_of_table = table.num_rows == 0;
-        Self { table, row_num: 0, end_of_table }
+impl<'a, 'db> Cursor<'a, 'db> {
+    pub fn new(table: &'a mut Table<'db>) -> Self {
+        let end_of_table = table.leaves.is_empty();
+        Self { table, leaf: 0, cell: 0, end_of_table }
     }
 
-    pub fn table_end(table: &'a mut Table) -> Self {
-        let row_num = table.num_rows;
-        Self { table, row_num, end_of_table: true }
+    /// A cursor positioned at the first row whose key is at least `key`.
+    pub fn at(table: &'a mut Table<'db>, key: i64) -> Result<Self> {
+        let Some((leaf, cell)) = table.seek(key)? else {
+            return Ok(Self::new(table));
+        };
+        Ok(Self { table, leaf, cell, end_of_table: false })
     }
 
     pub fn advance(&mut self) {
-        self.row_num += 1;
-        if self.row_num >= self.table.num_rows {
-            self.end_of_table = true;
-        }
+        self.cell += 1;
     }
 
-    pub fn value(&mut self) -> &mut Page {
-        let page_num = self.row_num / ROWS_PER_PAGE;
-        assert!(page_num < TABLE_MAX_PAGES);
-
-        self.table.get_page_mut(page_num)
-    }
-
-    pub fn byte_offset(&self) -> usize {
-        let row_offset = self.row_num % ROWS_PER_PAGE;
-        row_offset * ROW_SIZE
+    /// Whether the cursor is known to be past the last row. Reading the row
+    /// under the cursor finds out when it moves past the end.
+    pub fn end_of_table(&self) -> bool {
+        self.end_of_table
     }
 }
@@ -0,0 +1,550 @@
+use crate::ast::{AlterAction, AlterTable, ColumnConstraint, CreateTable, Expr};
+use crate::cursor::Cursor;
+use crate::error::{Constraint, Error, Limit, Object, Result};
+use crate::eval;
+use crate::header::{FileHeader, HEADER_SIZE, SCHEMA_ROOT_PAGE};
+use crate::persistence::{Pager, PagerConfig};
+use crate::schema::{CATALOG_SCHEMA, CATALOG_TABLE_NAME, Schema};
+use crate::table::{cursor_row, delete_row, insert_row, Table};
+use crate::value::Value;
+use std::fs::{File, OpenOptions};
+use std::io::Read;
+use std::path::Path;
+
+/// The savepoint each statement runs inside of, named so that no
+/// `SAVEPOINT` statement can refer to it.
+const STATEMENT_SAVEPOINT: &str = "\0statement";
+
+/// One row of the `rustqlite_schema` catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    /// `table` or `index`.
+    pub kind: String,
+    pub name: String,
+    /// The table an index belongs to; a table's own name for tables.
+    pub table_name: String,
+    pub root_page: usize,
+    /// The statement that created the object.
+    pub sql: String,
+    /// The parsed definition of a table.
+    pub schema: Option<Schema>,
+    /// The catalog row the entry is stored in.
+    pub rowid: i64,
+}
+
+/// An open database file and its catalog of tables.
+pub struct Database {
+    pub pager: Pager,
+    pub catalog: Vec<CatalogEntry>,
+    /// Set between `BEGIN` and `COMMIT` or `ROLLBACK`. Changes made meanwhile
+    /// stay in the page cache instead of being written after each statement.
+    in_transaction: bool,
+    /// Whether the transaction was opened by a `SAVEPOINT`, and so ends
+    /// when that savepoint is released.
+    savepoint_transaction: bool,
+}
+
+impl Database {
+    /// Opens or creates a database with the default settings.
+    #[cfg(test)]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with(path, &PagerConfig::default())
+    }
+
+    /// Whether the file at `path` is a database its header says is
+    /// encrypted. A file that can't be read as a database isn't.
+    pub fn is_encrypted(path: impl AsRef<Path>) -> bool {
+        let mut bytes = [0u8; HEADER_SIZE];
+        File::open(path)
+            .and_then(|mut file| file.read_exact(&mut bytes))
+            .and_then(|()| FileHeader::read_from(&bytes))
+            .is_ok_and(|header| header.is_encrypted())
+    }
+
+    /// Opens or creates a database and loads its catalog. New databases
+    /// start out with the default `users` table.
+    pub fn open_with(path: impl AsRef<Path>, config: &PagerConfig) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(Error::io(format!("open {}", path.display())))?;
+
+        let pager = Pager::open(file, config).map_err(Error::io(format!("open {}", path.display())))?;
+        let is_new = pager.content_length == 0;
+        let mut db = Self {
+            pager,
+            catalog: Vec::new(),
+            in_transaction: false,
+            savepoint_transaction: false,
+        };
+
+        // A new database is written out right away, so a rollback never
+        // goes back to before it existed.
+        if is_new {
+            db.commit()?;
+        } else {
+            db.catalog = db.load_catalog()?;
+        }
+        Ok(db)
+    }
+
+    /// Creates the `users` table the tests work on, unless it exists.
+    #[cfg(test)]
+    pub fn create_users(&mut self) -> Result<()> {
+        let Ok(crate::ast::Statement::CreateTable(users)) = crate::parser::parse(crate::schema::USERS_SCHEMA) else {
+            unreachable!("the users schema is a CREATE TABLE statement");
+        };
+        self.create_table(&users)?;
+        self.commit()
+    }
+
+    /// Writes every change since the last commit to disk.
+    pub fn commit(&mut self) -> Result<()> {
+        self.pager.commit().map_err(Error::io("write changes to disk"))
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.in_transaction
+    }
+
+    /// Starts a transaction: until it ends, statements leave their changes
+    /// in the page cache.
+    pub fn begin(&mut self) -> Result<()> {
+        if self.in_transaction {
+            return Err(Error::misuse("Cannot start a transaction within a transaction."));
+        }
+        self.in_transaction = true;
+        Ok(())
+    }
+
+    /// Ends the transaction, writing everything it changed to disk.
+    pub fn commit_transaction(&mut self) -> Result<()> {
+        if !self.in_transaction {
+            return Err(no_transaction());
+        }
+        self.commit()?;
+        self.in_transaction = false;
+        self.savepoint_transaction = false;
+        Ok(())
+    }
+
+    /// Ends the transaction, discarding everything it changed. The catalog
+    /// is read back, as tables may have been created, altered or dropped.
+    pub fn rollback(&mut self) -> Result<()> {
+        if !self.in_transaction {
+            return Err(no_transaction());
+        }
+        self.in_transaction = false;
+        self.savepoint_transaction = false;
+        self.discard_changes()
+    }
+
+    /// Sets a savepoint called `name`. Outside of a transaction it starts
+    /// one, which releasing the savepoint commits.
+    pub fn savepoint(&mut self, name: &str) {
+        if !self.in_transaction {
+            self.in_transaction = true;
+            self.savepoint_transaction = true;
+        }
+        self.pager.begin_savepoint(name);
+    }
+
+    /// Ends the savepoint called `name` and those set after it, keeping
+    /// their changes in the transaction.
+    pub fn release(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .pager
+            .find_savepoint(name)
+            .ok_or_else(|| Error::not_found(Object::Savepoint, name))?;
+        self.pager.release_savepoint(index);
+        if index == 0 && self.savepoint_transaction {
+            return self.commit_transaction();
+        }
+        Ok(())
+    }
+
+    /// Undoes what changed since the savepoint called `name` was set, which
+    /// stays open. The rest of the transaction is kept.
+    pub fn rollback_to(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .pager
+            .find_savepoint(name)
+            .ok_or_else(|| Error::not_found(Object::Savepoint, name))?;
+        self.pager.rollback_to_savepoint(index);
+        self.catalog = self.load_catalog()?;
+        Ok(())
+    }
+
+    /// Runs `statement` as one unit: if it fails partway, what it changed
+    /// is undone and the rest of the transaction is kept.
+    pub fn atomically<T>(&mut self, statement: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.pager.begin_savepoint(STATEMENT_SAVEPOINT);
+        let index = self.pager.savepoints.len() - 1;
+        let result = statement(self);
+        if result.is_err() {
+            self.pager.rollback_to_savepoint(index);
+        }
+        self.pager.release_savepoint(index);
+        if result.is_err() {
+            self.catalog = self.load_catalog()?;
+        }
+        result
+    }
+
+    /// Writes out what is left to write before the database is closed. A
+    /// transaction still open is rolled back, as it was never committed.
+    pub fn db_close(&mut self) -> Result<()> {
+        if self.in_transaction {
+            self.in_transaction = false;
+            self.savepoint_transaction = false;
+            self.discard_changes()?;
+        }
+        self.commit()
+    }
+
+    /// Throws away every change since the last commit, such as what a
+    /// failed statement left behind, and reads the catalog back.
+    pub fn discard_changes(&mut self) -> Result<()> {
+        self.pager.rollback();
+        self.catalog = self.load_catalog()?;
+        Ok(())
+    }
+
+    /// Opens the table called `name`, which may be the catalog itself.
+    pub fn table(&mut self, name: &str) -> Result<Table<'_>> {
+        if name.eq_ignore_ascii_case(CATALOG_TABLE_NAME) {
+            return self.catalog_table();
+        }
+        let entry = self
+            .catalog
+            .iter()
+            .find(|entry| entry.kind == "table" && entry.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::not_found(Object::Table, name))?;
+        let schema = entry.schema.clone().expect("tables have a schema");
+        Table::open(&mut self.pager, schema, entry.root_page)
+    }
+
+    /// Like `table`, but refuses the catalog, which only changes through
+    /// schema statements.
+    pub fn writable_table(&mut self, name: &str) -> Result<Table<'_>> {
+        if name.eq_ignore_ascii_case(CATALOG_TABLE_NAME) {
+            return Err(Error::misuse(format!("Table {CATALOG_TABLE_NAME} may not be modified.")));
+        }
+        self.table(name)
+    }
+
+    /// Creates the table `create` defines and records it in the catalog.
+    pub fn create_table(&mut self, create: &CreateTable) -> Result<()> {
+        let exists = create.name.eq_ignore_ascii_case(CATALOG_TABLE_NAME)
+            || self.catalog.iter().any(|entry| entry.name.eq_ignore_ascii_case(&create.name));
+        if exists {
+            if create.if_not_exists {
+                return Ok(());
+            }
+            return Err(Error::AlreadyExists(Object::Table, create.name.clone()));
+        }
+
+        let schema = Schema::new(create)?;
+        let root_page = self.pager.allocate_page().ok_or(Limit::TableFull)?;
+        let rowid = self.catalog_table()?.next_rowid()?;
+        let entry = CatalogEntry {
+            kind: String::from("table"),
+            name: create.name.clone(),
+            table_name: create.name.clone(),
+            root_page,
+            sql: schema.sql(),
+            schema: Some(schema),
+            rowid,
+        };
+        self.store_entry(&entry)?;
+        self.catalog.push(entry);
+        Ok(())
+    }
+
+    /// Changes the definition of a table. Adding a column only touches the
+    /// catalog, as rows stored before it read the column's default; dropping
+    /// one rewrites every row. The catalog entry is replaced last, so a
+    /// failed statement leaves both the file and the open catalog as they
+    /// were.
+    pub fn alter_table(&mut self, alter: &AlterTable) -> Result<()> {
+        let index = self
+            .catalog
+            .iter()
+            .position(|entry| entry.kind == "table" && entry.name.eq_ignore_ascii_case(&alter.table))
+            .ok_or_else(|| Error::not_found(Object::Table, &alter.table))?;
+        let old = self.catalog[index].schema.clone().expect("tables have a schema");
+        let mut create = old.create.clone();
+
+        match &alter.action {
+            AlterAction::AddColumn(def) => {
+                let cannot_add = |reason| Error::misuse(format!("Cannot add column {}: {reason}.", def.name));
+                for constraint in &def.constraints {
+                    match constraint {
+                        ColumnConstraint::PrimaryKey { .. } => return Err(cannot_add("it is a PRIMARY KEY")),
+                        ColumnConstraint::Unique => return Err(cannot_add("it is UNIQUE")),
+                        _ => {}
+                    }
+                }
+                create.columns.push(def.clone());
+                let schema = Schema::new(&create)?;
+                let added = schema.columns.last().unwrap();
+                if added.not_null && added.default_value() == Value::Null {
+                    return Err(cannot_add("it is NOT NULL without a default"));
+                }
+                self.check_added_column(&alter.table, &schema)?;
+            }
+            AlterAction::RenameColumn { from, to } => {
+                let column = old.column_index(from).ok_or_else(|| Error::not_found(Object::Column, from))?;
+                create.columns[column].name = to.clone();
+                let checks = create.columns.iter_mut().flat_map(|def| &mut def.constraints);
+                for constraint in checks {
+                    if let ColumnConstraint::Check(check) = constraint {
+                        rename_column(check, from, to);
+                    }
+                }
+            }
+            AlterAction::RenameTable(name) => {
+                let taken = name.eq_ignore_ascii_case(CATALOG_TABLE_NAME)
+                    || self
+                        .catalog
+                        .iter()
+                        .enumerate()
+                        .any(|(i, entry)| i != index && entry.name.eq_ignore_ascii_case(name));
+                if taken {
+                    return Err(Error::AlreadyExists(Object::Table, name.clone()));
+                }
+                create.name = name.clone();
+            }
+            AlterAction::DropColumn(name) => {
+                let column = old.column_index(name).ok_or_else(|| Error::not_found(Object::Column, name))?;
+                let cannot_drop = |reason| Error::misuse(format!("Cannot drop column {name}: {reason}."));
+                if old.key == Some(column) {
+                    return Err(cannot_drop("it is the PRIMARY KEY"));
+                }
+                if old.columns[column].unique {
+                    return Err(cannot_drop("it is UNIQUE"));
+                }
+                if old.columns.len() == 1 {
+                    return Err(cannot_drop("it is the only column"));
+                }
+                create.columns.remove(column);
+                if let Err(Error::NotFound(Object::Column, _)) = Schema::new(&create) {
+                    return Err(cannot_drop("a CHECK constraint refers to it"));
+                }
+            }
+        }
+        let schema = Schema::new(&create)?;
+
+        if let AlterAction::DropColumn(name) = &alter.action {
+            let column = old.column_index(name).unwrap();
+            self.drop_values(self.catalog[index].root_page, old, column)?;
+        }
+
+        let entry = &self.catalog[index];
+        let entry = CatalogEntry {
+            kind: entry.kind.clone(),
+            name: create.name.clone(),
+            table_name: create.name.clone(),
+            root_page: entry.root_page,
+            sql: schema.sql(),
+            schema: Some(schema),
+            rowid: entry.rowid,
+        };
+        self.store_entry(&entry)?;
+        self.catalog[index] = entry;
+        Ok(())
+    }
+
+    /// Checks the last column of `schema`, about to be added to `table`,
+    /// against the rows already there, which would take its default.
+    fn check_added_column(&mut self, table: &str, schema: &Schema) -> Result<()> {
+        let added = schema.columns.last().expect("a column was added");
+        if added.checks.is_empty() {
+            return Ok(());
+        }
+        let default = added.default_value();
+        let mut table = self.table(table)?;
+        let mut cursor = Cursor::new(&mut table);
+        while !cursor.end_of_table() {
+            if let Some(row) = cursor_row(&mut cursor)? {
+                let mut values = row.values;
+                values.push(default.clone());
+                let lookup = |name: &str| schema.column_index(name).map(|index| values[index].clone());
+                for check in &added.checks {
+                    if eval::evaluate(check, &lookup)?.truth() == Some(false) {
+                        return Err(Constraint::Check {
+                            column: added.name.clone(),
+                            check: check.to_string(),
+                        }
+                        .into());
+                    }
+                }
+            }
+            cursor.advance();
+        }
+        Ok(())
+    }
+
+    /// Drops the table called `name` along with its indexes, handing all of
+    /// their pages back to the freelist.
+    pub fn drop_table(&mut self, name: &str, if_exists: bool) -> Result<()> {
+        if name.eq_ignore_ascii_case(CATALOG_TABLE_NAME) {
+            return Err(Error::misuse(format!("Table {CATALOG_TABLE_NAME} may not be dropped.")));
+        }
+        let Some(table) = self
+            .catalog
+            .iter()
+            .find(|entry| entry.kind == "table" && entry.name.eq_ignore_ascii_case(name))
+        else {
+            return match if_exists {
+                true => Ok(()),
+                false => Err(Error::not_found(Object::Table, name)),
+            };
+        };
+        let table_name = table.name.clone();
+        let dropped: Vec<CatalogEntry> = self
+            .catalog
+            .iter()
+            .filter(|entry| entry.table_name.eq_ignore_ascii_case(&table_name))
+            .cloned()
+            .collect();
+        self.drop_entries(&dropped)
+    }
+
+    /// Drops the index called `name`, handing its pages back to the freelist.
+    pub fn drop_index(&mut self, name: &str, if_exists: bool) -> Result<()> {
+        let index = self
+            .catalog
+            .iter()
+            .find(|entry| entry.kind == "index" && entry.name.eq_ignore_ascii_case(name))
+            .cloned();
+        match index {
+            Some(index) => self.drop_entries(&[index]),
+            None if if_exists => Ok(()),
+            None => Err(Error::not_found(Object::Index, name)),
+        }
+    }
+
+    /// Deletes the catalog rows of `entries` and frees their pages. The open
+    /// catalog only forgets them once all of that has worked.
+    fn drop_entries(&mut self, entries: &[CatalogEntry]) -> Result<()> {
+        for entry in entries {
+            let mut catalog = self.catalog_table()?;
+            delete_row(&mut Cursor::new(&mut catalog), entry.rowid)?;
+
+            // Indexes are laid out like tables, and walking the pages never
+            // looks at the rows, so any schema serves to open either.
+            let schema = Schema::from_sql(CATALOG_SCHEMA).expect("catalog schema is valid");
+            let mut tree = Table::open(&mut self.pager, schema, entry.root_page)?;
+            for page_num in tree.pages()? {
+                self.pager.free_page(page_num);
+            }
+        }
+        self.catalog.retain(|kept| !entries.iter().any(|entry| entry.rowid == kept.rowid));
+        Ok(())
+    }
+
+    /// Rewrites every row of the table at `root_page` without its `column`-th value.
+    fn drop_values(&mut self, root_page: usize, schema: Schema, column: usize) -> Result<()> {
+        let mut table = Table::open(&mut self.pager, schema, root_page)?;
+        let mut cursor = Cursor::new(&mut table);
+        let mut rows = Vec::new();
+        while !cursor.end_of_table() {
+            if let Some(row) = cursor_row(&mut cursor)? {
+                rows.push(row);
+            }
+            cursor.advance();
+        }
+        for mut row in rows {
+            row.values.remove(column);
+            insert_row(&mut cursor, row.key, &row.values)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `entry` to its row of the catalog table.
+    fn store_entry(&mut self, entry: &CatalogEntry) -> Result<()> {
+        let mut catalog = self.catalog_table()?;
+        let values = [
+            Value::Text(entry.kind.clone()),
+            Value::Text(entry.name.clone()),
+            Value::Text(entry.table_name.clone()),
+            Value::Integer(entry.root_page as i64),
+            Value::Text(entry.sql.clone()),
+        ];
+        insert_row(&mut Cursor::new(&mut catalog), entry.rowid, &values)
+    }
+
+    fn catalog_table(&mut self) -> Result<Table<'_>> {
+        let schema = Schema::from_sql(CATALOG_SCHEMA).expect("catalog schema is valid");
+        Table::open(&mut self.pager, schema, SCHEMA_ROOT_PAGE)
+    }
+
+    fn load_catalog(&mut self) -> Result<Vec<CatalogEntry>> {
+        let mut catalog = self.catalog_table()?;
+        let mut cursor = Cursor::new(&mut catalog);
+        let mut entries = Vec::new();
+        while !cursor.end_of_table() {
+            if let Some(row) = cursor_row(&mut cursor)? {
+                let text = |index: usize| match &row.values[index] {
+                    Value::Text(text) => Ok(text.clone()),
+                    _ => Err(Error::corrupt("catalog entry is missing a field")),
+                };
+                let Value::Integer(root_page) = row.values[3] else {
+                    return Err(Error::corrupt("catalog entry has no root page"));
+                };
+                let (kind, sql) = (text(0)?, text(4)?);
+                let schema = match kind.as_str() {
+                    "table" => Some(Schema::from_sql(&sql)?),
+                    _ => None,
+                };
+                entries.push(CatalogEntry {
+                    kind,
+                    name: text(1)?,
+                    table_name: text(2)?,
+                    root_page: root_page as usize,
+                    sql,
+                    schema,
+                    rowid: row.key,
+                });
+            }
+            cursor.advance();
+        }
+        Ok(entries)
+    }
+}
+
+fn no_transaction() -> Error {
+    Error::misuse("No transaction is active.")
+}
+
+/// Points the references to column `from` in `expr` at `to` instead.
+fn rename_column(expr: &mut Expr, from: &str, to: &str) {
+    match expr {
+        Expr::Literal(_) | Expr::Parameter(_) => {}
+        Expr::Column(name) => {
+            if name.eq_ignore_ascii_case(from) {
+                *name = to.to_string();
+            }
+        }
+        Expr::Unary { expr, .. } | Expr::IsNull { expr, .. } => rename_column(expr, from, to),
+        Expr::Binary { left, right, .. } => {
+            rename_column(left, from, to);
+            rename_column(right, from, to);
+        }
+        Expr::In { expr, list, .. } => {
+            rename_column(expr, from, to);
+            list.iter_mut().for_each(|item| rename_column(item, from, to));
+        }
+        Expr::Between { expr, low, high, .. } => {
+            for expr in [expr, low, high] {
+                rename_column(expr, from, to);
+            }
+        }
+        Expr::Function { args, .. } => args.iter_mut().for_each(|arg| rename_column(arg, from, to)),
+    }
+}
@@ -0,0 +1,227 @@
+use crate::lexer::SyntaxError;
+use std::fmt;
+use std::io;
+
+/// The result of anything that can fail with an [`Error`].
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong running a statement, from parsing it to
+/// writing its changes to disk.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The SQL doesn't parse.
+    Syntax(SyntaxError),
+    /// A row that would break one of its table's constraints.
+    Constraint(Constraint),
+    /// A statement names something that doesn't exist.
+    NotFound(Object, String),
+    /// A statement would create something that exists already.
+    AlreadyExists(Object, String),
+    /// A well-formed statement that can't be carried out, such as a
+    /// `COMMIT` outside of a transaction.
+    Misuse(String),
+    /// The file holds something the engine never writes.
+    Corrupt(String),
+    /// Reading or writing the file failed; `context` says what was being done.
+    Io {
+        /// What was being done, such as `open people.db`.
+        context: String,
+        /// The error the operating system gave.
+        source: io::Error,
+    },
+    /// The database has grown as far as it can.
+    Limit(Limit),
+    /// A value read from a row as a type it doesn't hold; `index` is its
+    /// column's, counting from 0.
+    Mismatch {
+        /// The column the value was read from.
+        index: usize,
+        /// The type asked for, such as `an integer`.
+        expected: &'static str,
+        /// The type of the value, such as `text`.
+        found: &'static str,
+    },
+}
+
+/// The constraint a row would break. Each names the column it is on.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Constraint {
+    /// A NULL in a `NOT NULL` column.
+    NotNull {
+        /// The column.
+        column: String,
+    },
+    /// A value another row of a `UNIQUE` or key column already holds.
+    Unique {
+        /// The column.
+        column: String,
+    },
+    /// A `CHECK` on the column came out false.
+    Check {
+        /// The column.
+        column: String,
+        /// The expression of the `CHECK`.
+        check: String,
+    },
+    /// A value the column can't hold, such as text for the rowid.
+    Datatype {
+        /// The column.
+        column: String,
+    },
+}
+
+/// The kind of thing an error names.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Object {
+    /// A table, by name.
+    Table,
+    /// A column of a table or of a query's rows, by name or by index.
+    Column,
+    /// An index, by name.
+    Index,
+    /// A function an expression calls.
+    Function,
+    /// A savepoint a `RELEASE` or `ROLLBACK TO` names.
+    Savepoint,
+    /// A row, by rowid.
+    Row,
+    /// A parameter of a statement, by index.
+    Parameter,
+}
+
+/// A limit of the file format that a statement ran into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Limit {
+    /// Every page the file may have is in use, or the table's root page has
+    /// no room left for another leaf.
+    TableFull,
+    /// A row inserted without a rowid would need one past `i64::MAX`.
+    RowidExhausted,
+}
+
+impl Error {
+    /// Wraps an I/O error with what was being done, for use with `map_err`.
+    pub fn io(context: impl Into<String>) -> impl FnOnce(io::Error) -> Self {
+        let context = context.into();
+        move |source| Error::Io { context, source }
+    }
+
+    /// An [`Error::Misuse`] saying `message`.
+    pub fn misuse(message: impl Into<String>) -> Self {
+        Error::Misuse(message.into())
+    }
+
+    /// An [`Error::Corrupt`] giving `reason`.
+    pub fn corrupt(reason: impl Into<String>) -> Self {
+        Error::Corrupt(reason.into())
+    }
+
+    /// An [`Error::NotFound`] for the `object` called `name`.
+    pub fn not_found(object: Object, name: impl Into<String>) -> Self {
+        Error::NotFound(object, name.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(err) => write!(f, "{err}"),
+            Error::Constraint(constraint) => write!(f, "{constraint}"),
+            Error::NotFound(object, name) => write!(f, "No such {object}: {name}."),
+            Error::AlreadyExists(Object::Column, name) => write!(f, "Duplicate column name: {name}."),
+            Error::AlreadyExists(object, name) => {
+                let object = object.to_string();
+                let (first, rest) = object.split_at(1);
+                write!(f, "{}{rest} {name} already exists.", first.to_ascii_uppercase())
+            }
+            Error::Misuse(message) => write!(f, "{message}"),
+            Error::Corrupt(reason) => write!(f, "Database is corrupt: {reason}."),
+            Error::Io { context, source } => write!(f, "Could not {context}: {source}."),
+            Error::Limit(Limit::TableFull) => write!(f, "Table full."),
+            Error::Limit(Limit::RowidExhausted) => write!(f, "No rowid is left for a new row."),
+            Error::Mismatch { index, expected, found } => {
+                write!(f, "Column {index} holds {found}, not {expected}.")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::NotNull { column } => write!(f, "NOT NULL constraint failed: {column}."),
+            Constraint::Unique { column } => write!(f, "UNIQUE constraint failed: {column}."),
+            Constraint::Check { column, check } => write!(f, "CHECK constraint failed on {column}: {check}."),
+            Constraint::Datatype { column } => write!(f, "Wrong type of value for {column}."),
+        }
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Object::Table => "table",
+            Object::Column => "column",
+            Object::Index => "index",
+            Object::Function => "function",
+            Object::Savepoint => "savepoint",
+            Object::Row => "row",
+            Object::Parameter => "parameter",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// I/O errors compare by kind, as `io::Error` itself can't be compared.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Error::Syntax(a), Error::Syntax(b)) => a == b,
+            (Error::Constraint(a), Error::Constraint(b)) => a == b,
+            (Error::NotFound(a, x), Error::NotFound(b, y)) => a == b && x == y,
+            (Error::AlreadyExists(a, x), Error::AlreadyExists(b, y)) => a == b && x == y,
+            (Error::Misuse(a), Error::Misuse(b)) => a == b,
+            (Error::Corrupt(a), Error::Corrupt(b)) => a == b,
+            (Error::Io { context: a, source: x }, Error::Io { context: b, source: y }) => {
+                a == b && x.kind() == y.kind()
+            }
+            (Error::Limit(a), Error::Limit(b)) => a == b,
+            (
+                Error::Mismatch { index: a, expected: x, found: p },
+                Error::Mismatch { index: b, expected: y, found: q },
+            ) => a == b && x == y && p == q,
+            _ => false,
+        }
+    }
+}
+
+impl From<SyntaxError> for Error {
+    fn from(err: SyntaxError) -> Self {
+        Error::Syntax(err)
+    }
+}
+
+impl From<Constraint> for Error {
+    fn from(constraint: Constraint) -> Self {
+        Error::Constraint(constraint)
+    }
+}
+
+impl From<Limit> for Error {
+    fn from(limit: Limit) -> Self {
+        Error::Limit(limit)
+    }
+}
@@ -0,0 +1,160 @@
+use crate::ast::{BinaryOp, Expr, UnaryOp};
+use crate::error::{Error, Object, Result};
+use crate::value::Value;
+use std::cmp::Ordering;
+
+/// Evaluates `expr` against one row, whose columns are looked up by name
+/// through `column`.
+pub fn evaluate(expr: &Expr, column: &dyn Fn(&str) -> Option<Value>) -> Result<Value> {
+    evaluate_with(expr, column, &|_, _| None)
+}
+
+/// Like `evaluate`, with the functions an expression may call looked up
+/// through `function`, given the name and the evaluated arguments.
+pub fn evaluate_with(
+    expr: &Expr,
+    column: &dyn Fn(&str) -> Option<Value>,
+    function: &dyn Fn(&str, &[Value]) -> Option<Value>,
+) -> Result<Value> {
+    let eval = |expr: &Expr| evaluate_with(expr, column, function);
+    Ok(match expr {
+        Expr::Literal(literal) => Value::from(literal),
+        Expr::Column(name) => column(name).ok_or_else(|| Error::not_found(Object::Column, name))?,
+        // Bound values replace parameters before a statement runs, so any
+        // left are unbound, and NULL.
+        Expr::Parameter(_) => Value::Null,
+        Expr::Unary { op: UnaryOp::Not, expr } => not(eval(expr)?.truth()),
+        Expr::Unary { op: UnaryOp::Negate, expr } => match eval(expr)?.numeric() {
+            Value::Integer(n) => n.checked_neg().map_or(Value::Real(-(n as f64)), Value::Integer),
+            Value::Real(n) => Value::Real(-n),
+            other => other,
+        },
+        Expr::Binary { op: BinaryOp::And, left, right } => {
+            match (eval(left)?.truth(), eval(right)?.truth()) {
+                (Some(false), _) | (_, Some(false)) => boolean(false),
+                (Some(true), Some(true)) => boolean(true),
+                _ => Value::Null,
+            }
+        }
+        Expr::Binary { op: BinaryOp::Or, left, right } => {
+            match (eval(left)?.truth(), eval(right)?.truth()) {
+                (Some(true), _) | (_, Some(true)) => boolean(true),
+                (Some(false), Some(false)) => boolean(false),
+                _ => Value::Null,
+            }
+        }
+        Expr::Binary { op, left, right } => binary(*op, eval(left)?, eval(right)?),
+        Expr::In { expr, list, negated } => {
+            let value = eval(expr)?;
+            let mut result = Some(false);
+            for item in list {
+                match value.compare(&eval(item)?) {
+                    Some(Ordering::Equal) => {
+                        result = Some(true);
+                        break;
+                    }
+                    Some(_) => {}
+                    None => result = None,
+                }
+            }
+            negate_if(*negated, result)
+        }
+        Expr::Between { expr, low, high, negated } => {
+            let value = eval(expr)?;
+            let above = value.compare(&eval(low)?).map(|o| o != Ordering::Less);
+            let below = value.compare(&eval(high)?).map(|o| o != Ordering::Greater);
+            let result = match (above, below) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            };
+            negate_if(*negated, result)
+        }
+        Expr::IsNull { expr, negated } => boolean(eval(expr)?.is_null() != *negated),
+        Expr::Function { name, args } => {
+            let args = args.iter().map(eval).collect::<Result<Vec<_>>>()?;
+            function(name, &args).ok_or_else(|| Error::not_found(Object::Function, name))?
+        }
+    })
+}
+
+/// Whether `expr` holds for a row. Unknown (NULL) counts as not matching.
+pub fn matches(expr: &Expr, column: &dyn Fn(&str) -> Option<Value>) -> Result<bool> {
+    Ok(evaluate(expr, column)?.truth() == Some(true))
+}
+
+fn binary(op: BinaryOp, left: Value, right: Value) -> Value {
+    if left.is_null() || right.is_null() {
+        return Value::Null;
+    }
+    // Values that don't compare, such as NaN, give NULL like NULL itself.
+    let compare = |holds: fn(Ordering) -> bool| left.compare(&right).map_or(Value::Null, |o| boolean(holds(o)));
+    match op {
+        BinaryOp::Eq => compare(Ordering::is_eq),
+        BinaryOp::NotEq => compare(Ordering::is_ne),
+        BinaryOp::Lt => compare(Ordering::is_lt),
+        BinaryOp::LtEq => compare(Ordering::is_le),
+        BinaryOp::Gt => compare(Ordering::is_gt),
+        BinaryOp::GtEq => compare(Ordering::is_ge),
+        BinaryOp::Concat => Value::Text(format!("{left}{right}")),
+        BinaryOp::And | BinaryOp::Or => unreachable!("logical operators are handled in evaluate"),
+        op => arithmetic(op, left.numeric(), right.numeric()),
+    }
+}
+
+/// Integer arithmetic falls back to reals on overflow; dividing by zero is
+/// NULL, as is a result that isn't a number, such as infinity minus itself.
+fn arithmetic(op: BinaryOp, left: Value, right: Value) -> Value {
+    if let (Value::Integer(a), Value::Integer(b)) = (&left, &right) {
+        let (a, b) = (*a, *b);
+        let result = match op {
+            BinaryOp::Add => a.checked_add(b),
+            BinaryOp::Subtract => a.checked_sub(b),
+            BinaryOp::Multiply => a.checked_mul(b),
+            BinaryOp::Divide if b == 0 => return Value::Null,
+            BinaryOp::Divide => a.checked_div(b),
+            BinaryOp::Modulo if b == 0 => return Value::Null,
+            BinaryOp::Modulo => Some(a.checked_rem(b).unwrap_or(0)),
+            _ => unreachable!("not an arithmetic operator"),
+        };
+        if let Some(n) = result {
+            return Value::Integer(n);
+        }
+    }
+
+    let as_real = |value: &Value| match value {
+        Value::Integer(n) => *n as f64,
+        Value::Real(n) => *n,
+        _ => 0.0,
+    };
+    let (a, b) = (as_real(&left), as_real(&right));
+    let result = match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Subtract => a - b,
+        BinaryOp::Multiply => a * b,
+        BinaryOp::Divide if b == 0.0 => return Value::Null,
+        BinaryOp::Divide => a / b,
+        BinaryOp::Modulo if b == 0.0 => return Value::Null,
+        BinaryOp::Modulo => a % b,
+        _ => unreachable!("not an arithmetic operator"),
+    };
+    match result.is_nan() {
+        true => Value::Null,
+        false => Value::Real(result),
+    }
+}
+
+fn boolean(b: bool) -> Value {
+    Value::Integer(b as i64)
+}
+
+fn not(truth: Option<bool>) -> Value {
+    truth.map_or(Value::Null, |b| boolean(!b))
+}
+
+fn negate_if(negated: bool, result: Option<bool>) -> Value {
+    match result {
+        Some(b) => boolean(b != negated),
+        None => Value::Null,
+    }
+}
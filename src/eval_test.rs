@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use crate::ast::Statement;
+    use crate::error::{Error, Object, Result};
+    use crate::eval::evaluate;
+    use crate::parser::parse;
+    use crate::value::Value;
+
+    /// Evaluates the WHERE clause of `SELECT * FROM users WHERE <expr>` against
+    /// a row with `id = 7, name = 'alice', email = NULL`.
+    fn eval(expr: &str) -> Result<Value> {
+        let Ok(Statement::Select(select)) = parse(&format!("SELECT * FROM users WHERE {expr}")) else {
+            panic!("failed to parse {expr}");
+        };
+        let row = |column: &str| match column {
+            "id" => Some(Value::Integer(7)),
+            "name" => Some(Value::Text(String::from("alice"))),
+            "email" => Some(Value::Null),
+            _ => None,
+        };
+        evaluate(&select.where_clause.unwrap(), &row)
+    }
+
+    #[test]
+    fn arithmetic_follows_precedence_and_promotes_to_real() {
+        assert_eq!(eval("1 + id * 2 - 10 / 4"), Ok(Value::Integer(13)));
+        assert_eq!(eval("(1 + id) % 3"), Ok(Value::Integer(2)));
+        assert_eq!(eval("id / 2.0"), Ok(Value::Real(3.5)));
+        assert_eq!(eval("-id"), Ok(Value::Integer(-7)));
+        assert_eq!(eval("id / 0"), Ok(Value::Null));
+        assert_eq!(eval("1e308 * 10 - 1e308 * 10 = 0"), Ok(Value::Null));
+        assert_eq!(eval("'12abc' + 1"), Ok(Value::Integer(13)));
+        assert_eq!(eval("name || '@' || id"), Ok(Value::Text(String::from("alice@7"))));
+    }
+
+    #[test]
+    fn comparisons_and_logic_use_three_valued_nulls() {
+        assert_eq!(eval("id >= 7 AND name = 'alice'"), Ok(Value::Integer(1)));
+        assert_eq!(eval("id < 7 OR NOT name <> 'alice'"), Ok(Value::Integer(1)));
+        assert_eq!(eval("email = 'x'"), Ok(Value::Null));
+        assert_eq!(eval("email = 'x' AND id = 0"), Ok(Value::Integer(0)));
+        assert_eq!(eval("email = 'x' OR id = 7"), Ok(Value::Integer(1)));
+        assert_eq!(eval("NOT email = 'x'"), Ok(Value::Null));
+        assert_eq!(eval("email IS NULL AND name IS NOT NULL"), Ok(Value::Integer(1)));
+        assert_eq!(eval("id < 'a'"), Ok(Value::Integer(1)));
+    }
+
+    #[test]
+    fn in_and_between() {
+        assert_eq!(eval("id IN (1, 7, 9)"), Ok(Value::Integer(1)));
+        assert_eq!(eval("id NOT IN (1, 2)"), Ok(Value::Integer(1)));
+        assert_eq!(eval("id IN (1, NULL)"), Ok(Value::Null));
+        assert_eq!(eval("id BETWEEN 1 + 1 AND 7 AND name = 'alice'"), Ok(Value::Integer(1)));
+        assert_eq!(eval("id NOT BETWEEN 1 AND 6"), Ok(Value::Integer(1)));
+    }
+
+    #[test]
+    fn unknown_columns_are_errors() {
+        assert_eq!(eval("age > 3"), Err(Error::NotFound(Object::Column, String::from("age"))));
+    }
+}
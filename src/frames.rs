@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// In framed mode pages are not stored at `page_num * page_size` but in
+/// variable-size frames following the header page:
+///
+/// `[page_num: u32][capacity: u32][len: u32][capacity bytes of data]`
+///
+/// Only the first `len` data bytes are meaningful. A frame whose page is
+/// rewritten with data that no longer fits is marked free and reused later.
+pub const FRAME_HEADER_SIZE: usize = 3 * size_of::<u32>();
+const FREE_FRAME: u32 = u32::MAX;
+/// New frames get some slack so a page can grow a little in place.
+const FRAME_ALIGN: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    offset: u64,
+    capacity: usize,
+    len: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct FrameMap {
+    frames: HashMap<usize, Frame>,
+    free: Vec<Frame>,
+    end: u64,
+}
+
+impl FrameMap {
+    /// Builds the map by walking every frame between `start` and the end of the file.
+    pub fn scan(file: &mut File, start: u64, file_length: u64) -> io::Result<Self> {
+        let mut map = FrameMap::default();
+
+        let mut offset = start;
+        while offset < file_length {
+            let mut header = [0u8; FRAME_HEADER_SIZE];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut header)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "truncated page frame"))?;
+
+            let page_num = read_u32(&header, 0);
+            let frame = Frame {
+                offset,
+                capacity: read_u32(&header, 4) as usize,
+                len: read_u32(&header, 8) as usize,
+            };
+            if frame.len > frame.capacity {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "corrupt page frame"));
+            }
+
+            if page_num == FREE_FRAME {
+                map.free.push(frame);
+            } else if let Some(stale) = map.frames.insert(page_num as usize, frame) {
+                map.free.push(stale);
+            }
+            offset += (FRAME_HEADER_SIZE + frame.capacity) as u64;
+        }
+
+        // The last frame's slack may not have been written out yet.
+        map.end = offset.max(start);
+        Ok(map)
+    }
+
+    /// Reads the stored bytes of `page_num`, or `None` if it was never written.
+    pub fn read(&self, file: &mut File, page_num: usize) -> io::Result<Option<Vec<u8>>> {
+        let Some(frame) = self.frames.get(&page_num) else {
+            return Ok(None);
+        };
+        let mut data = vec![0u8; frame.len];
+        file.seek(SeekFrom::Start(frame.offset + FRAME_HEADER_SIZE as u64))?;
+        file.read_exact(&mut data)?;
+        Ok(Some(data))
+    }
+
+    /// Stores `data` for `page_num`, in place if its frame is big enough and
+    /// in a free or newly appended frame otherwise.
+    pub fn write(&mut self, file: &mut File, page_num: usize, data: &[u8]) -> io::Result<()> {
+        let old = self.frames.get(&page_num).copied();
+        let frame = match old {
+            Some(frame) if frame.capacity >= data.len() => frame,
+            _ => self.take_frame(data.len()),
+        };
+
+        write_frame_header(file, frame.offset, page_num as u32, frame.capacity, data.len())?;
+        file.write_all(data)?;
+        self.frames.insert(page_num, Frame { len: data.len(), ..frame });
+
+        // Only give up the old frame once the page is safely in its new one.
+        if let Some(old) = old
+            && old.offset != frame.offset
+        {
+            write_frame_header(file, old.offset, FREE_FRAME, old.capacity, 0)?;
+            self.free.push(old);
+        }
+        Ok(())
+    }
+
+    /// Total bytes of page data held in live frames.
+    pub fn stored_bytes(&self) -> usize {
+        self.frames.values().map(|frame| frame.len).sum()
+    }
+
+    pub fn page_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn take_frame(&mut self, len: usize) -> Frame {
+        if let Some(idx) = self.free.iter().position(|frame| frame.capacity >= len) {
+            return self.free.swap_remove(idx);
+        }
+        let frame = Frame {
+            offset: self.end,
+            capacity: len.next_multiple_of(FRAME_ALIGN),
+            len: 0,
+        };
+        self.end += (FRAME_HEADER_SIZE + frame.capacity) as u64;
+        frame
+    }
+}
+
+fn write_frame_header(
+    file: &mut File,
+    offset: u64,
+    page_num: u32,
+    capacity: usize,
+    len: usize,
+) -> io::Result<()> {
+    let mut header = [0u8; FRAME_HEADER_SIZE];
+    header[0..4].copy_from_slice(&page_num.to_le_bytes());
+    header[4..8].copy_from_slice(&(capacity as u32).to_le_bytes());
+    header[8..12].copy_from_slice(&(len as u32).to_le_bytes());
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(&header)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
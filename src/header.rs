@@ -0,0 +1,135 @@
+use crate::crypto::{KEY_CHECK_SIZE, SALT_SIZE};
+use std::io;
+
+pub const HEADER_MAGIC: &[u8; 16] = b"rustqlite fmt 1\0";
+/// Bytes at the start of page 0 reserved for the header. The rest of page 0
+/// is unused so that data pages stay aligned to the page size.
+pub const HEADER_SIZE: usize = 100;
+
+/// The page size of a new database unless another is asked for.
+pub const DEFAULT_PAGE_SIZE: usize = 4096;
+pub const MIN_PAGE_SIZE: usize = 512;
+pub const MAX_PAGE_SIZE: usize = 65536;
+
+const MAGIC_OFFSET: usize = 0;
+const PAGE_SIZE_OFFSET: usize = MAGIC_OFFSET + HEADER_MAGIC.len();
+const PAGE_COUNT_OFFSET: usize = PAGE_SIZE_OFFSET + size_of::<u32>();
+const FLAGS_OFFSET: usize = PAGE_COUNT_OFFSET + size_of::<u32>();
+const SALT_OFFSET: usize = FLAGS_OFFSET + size_of::<u32>();
+const KEY_CHECK_OFFSET: usize = SALT_OFFSET + SALT_SIZE;
+const FIRST_FREE_PAGE_OFFSET: usize = KEY_CHECK_OFFSET + KEY_CHECK_SIZE;
+const FREE_PAGE_COUNT_OFFSET: usize = FIRST_FREE_PAGE_OFFSET + size_of::<u32>();
+
+/// Pages are LZ4-compressed and stored in variable-size frames.
+pub const FLAG_COMPRESSED: u32 = 1 << 0;
+/// Pages are encrypted and stored in variable-size frames. The header itself
+/// stays in plaintext and only records the salt and key check value.
+pub const FLAG_ENCRYPTED: u32 = 1 << 1;
+
+/// Page 1 is the root page of the `rustqlite_schema` catalog table, which
+/// records where every other table is rooted.
+pub const SCHEMA_ROOT_PAGE: usize = 1;
+
+/// The database file header, stored in the first bytes of page 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHeader {
+    pub page_size: usize,
+    /// Number of pages allocated in the file, including the header page.
+    pub page_count: usize,
+    /// `FLAG_*` bits, fixed when the database is created.
+    pub flags: u32,
+    /// Key derivation salt, only meaningful with `FLAG_ENCRYPTED`.
+    pub salt: [u8; SALT_SIZE],
+    /// Encrypted empty message used to verify a key, see `PageCipher::open`.
+    pub key_check: [u8; KEY_CHECK_SIZE],
+    /// Head of the freelist, the pages no object uses any more; `0` when it
+    /// is empty. Each free page starts with the number of the next one.
+    pub first_free_page: usize,
+    pub free_page_count: usize,
+}
+
+impl FileHeader {
+    pub fn new(page_size: usize) -> io::Result<Self> {
+        validate_page_size(page_size)?;
+        Ok(Self {
+            page_size,
+            page_count: SCHEMA_ROOT_PAGE + 1,
+            flags: 0,
+            salt: [0; SALT_SIZE],
+            key_check: [0; KEY_CHECK_SIZE],
+            first_free_page: 0,
+            free_page_count: 0,
+        })
+    }
+
+    pub fn read_from(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < HEADER_SIZE || &bytes[..HEADER_MAGIC.len()] != HEADER_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "file is not a rustqlite database",
+            ));
+        }
+
+        let page_size = read_u32(bytes, PAGE_SIZE_OFFSET) as usize;
+        validate_page_size(page_size)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        Ok(Self {
+            page_size,
+            page_count: read_u32(bytes, PAGE_COUNT_OFFSET) as usize,
+            flags: read_u32(bytes, FLAGS_OFFSET),
+            salt: bytes[SALT_OFFSET..SALT_OFFSET + SALT_SIZE].try_into().unwrap(),
+            key_check: bytes[KEY_CHECK_OFFSET..KEY_CHECK_OFFSET + KEY_CHECK_SIZE]
+                .try_into()
+                .unwrap(),
+            first_free_page: read_u32(bytes, FIRST_FREE_PAGE_OFFSET) as usize,
+            free_page_count: read_u32(bytes, FREE_PAGE_COUNT_OFFSET) as usize,
+        })
+    }
+
+    pub fn write_to(&self, bytes: &mut [u8]) {
+        bytes[..HEADER_SIZE].fill(0);
+        bytes[MAGIC_OFFSET..MAGIC_OFFSET + HEADER_MAGIC.len()].copy_from_slice(HEADER_MAGIC);
+        bytes[PAGE_SIZE_OFFSET..PAGE_SIZE_OFFSET + 4]
+            .copy_from_slice(&(self.page_size as u32).to_le_bytes());
+        bytes[PAGE_COUNT_OFFSET..PAGE_COUNT_OFFSET + 4]
+            .copy_from_slice(&(self.page_count as u32).to_le_bytes());
+        bytes[FLAGS_OFFSET..FLAGS_OFFSET + 4].copy_from_slice(&self.flags.to_le_bytes());
+        bytes[SALT_OFFSET..SALT_OFFSET + SALT_SIZE].copy_from_slice(&self.salt);
+        bytes[KEY_CHECK_OFFSET..KEY_CHECK_OFFSET + KEY_CHECK_SIZE].copy_from_slice(&self.key_check);
+        bytes[FIRST_FREE_PAGE_OFFSET..FIRST_FREE_PAGE_OFFSET + 4]
+            .copy_from_slice(&(self.first_free_page as u32).to_le_bytes());
+        bytes[FREE_PAGE_COUNT_OFFSET..FREE_PAGE_COUNT_OFFSET + 4]
+            .copy_from_slice(&(self.free_page_count as u32).to_le_bytes());
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags & FLAG_COMPRESSED != 0
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    /// Whether pages are stored in frames rather than at fixed offsets.
+    pub fn is_framed(&self) -> bool {
+        self.is_compressed() || self.is_encrypted()
+    }
+}
+
+/// Page sizes must be a power of two between `MIN_PAGE_SIZE` and `MAX_PAGE_SIZE`.
+pub fn validate_page_size(page_size: usize) -> io::Result<()> {
+    if !page_size.is_power_of_two() || !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&page_size) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "page size must be a power of two between {MIN_PAGE_SIZE} and {MAX_PAGE_SIZE}, got {page_size}"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
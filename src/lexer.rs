@@ -0,0 +1,323 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// A bare identifier or keyword; keywords are matched case-insensitively by the parser.
+    Word(String),
+    /// An identifier in double quotes, backticks or brackets. Never a keyword.
+    QuotedIdent(String),
+    /// The digits of an integer literal. Past `i64::MAX` it reads as a real,
+    /// unless a minus makes it `i64::MIN`.
+    Integer(u64),
+    Real(f64),
+    String(String),
+    /// `X'...'`, hex digits spelling out the bytes of a blob.
+    Blob(Vec<u8>),
+    /// A placeholder for a bound value, as written: `?`, `?NNN` or `:name`.
+    Parameter(String),
+    LParen,
+    RParen,
+    Comma,
+    Semicolon,
+    Dot,
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Concat,
+    Eof,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            TokenKind::Word(word) => return write!(f, "'{word}'"),
+            TokenKind::QuotedIdent(name) => return write!(f, "\"{name}\""),
+            TokenKind::Integer(n) => return write!(f, "{n}"),
+            TokenKind::Real(n) => return write!(f, "{n}"),
+            TokenKind::String(s) => return write!(f, "string '{s}'"),
+            TokenKind::Blob(bytes) => return write!(f, "blob of {} byte(s)", bytes.len()),
+            TokenKind::Parameter(name) => return write!(f, "parameter {name}"),
+            TokenKind::Eof => return write!(f, "end of input"),
+            TokenKind::LParen => "(",
+            TokenKind::RParen => ")",
+            TokenKind::Comma => ",",
+            TokenKind::Semicolon => ";",
+            TokenKind::Dot => ".",
+            TokenKind::Star => "*",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Slash => "/",
+            TokenKind::Percent => "%",
+            TokenKind::Eq => "=",
+            TokenKind::NotEq => "!=",
+            TokenKind::Lt => "<",
+            TokenKind::LtEq => "<=",
+            TokenKind::Gt => ">",
+            TokenKind::GtEq => ">=",
+            TokenKind::Concat => "||",
+        };
+        write!(f, "'{symbol}'")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// A lexing or parsing error, positioned at a 1-based line and column.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SyntaxError {
+    /// What is wrong, such as `expected an identifier, found ','`.
+    pub message: String,
+    /// The line of the offending token, counting from 1.
+    pub line: usize,
+    /// The column of the offending token, counting from 1.
+    pub col: usize,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "syntax error at line {}, column {}: {}",
+            self.line, self.col, self.message
+        )
+    }
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<Token>, SyntaxError> {
+    Lexer::new(input).tokenize()
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            chars: input.chars().peekable(),
+            line: 1,
+            col: 1,
+        }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, SyntaxError> {
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments();
+            let (line, col) = (self.line, self.col);
+            let Some(c) = self.bump() else {
+                tokens.push(Token { kind: TokenKind::Eof, line, col });
+                return Ok(tokens);
+            };
+
+            let kind = match c {
+                '(' => TokenKind::LParen,
+                ')' => TokenKind::RParen,
+                ',' => TokenKind::Comma,
+                ';' => TokenKind::Semicolon,
+                '.' if !self.peek_is(|c| c.is_ascii_digit()) => TokenKind::Dot,
+                '*' => TokenKind::Star,
+                '+' => TokenKind::Plus,
+                '-' => TokenKind::Minus,
+                '/' => TokenKind::Slash,
+                '%' => TokenKind::Percent,
+                '=' => {
+                    self.eat('=');
+                    TokenKind::Eq
+                }
+                '!' if self.eat('=') => TokenKind::NotEq,
+                '<' if self.eat('=') => TokenKind::LtEq,
+                '<' if self.eat('>') => TokenKind::NotEq,
+                '<' => TokenKind::Lt,
+                '>' if self.eat('=') => TokenKind::GtEq,
+                '>' => TokenKind::Gt,
+                '|' if self.eat('|') => TokenKind::Concat,
+                '?' => TokenKind::Parameter(self.parameter(c, |c| c.is_ascii_digit())),
+                ':' if self.peek_is(|c| c.is_alphanumeric() || c == '_') => {
+                    TokenKind::Parameter(self.parameter(c, |c| c.is_alphanumeric() || c == '_'))
+                }
+                '\'' => TokenKind::String(self.quoted('\'', line, col)?),
+                '"' => TokenKind::QuotedIdent(self.quoted('"', line, col)?),
+                '`' => TokenKind::QuotedIdent(self.quoted('`', line, col)?),
+                '[' => TokenKind::QuotedIdent(self.quoted(']', line, col)?),
+                c if c.is_ascii_digit() || c == '.' => self.number(c, line, col)?,
+                'x' | 'X' if self.peek_is(|c| c == '\'') => {
+                    self.bump();
+                    let hex = self.quoted('\'', line, col)?;
+                    TokenKind::Blob(parse_hex(&hex).ok_or_else(|| SyntaxError {
+                        message: format!("malformed blob literal X'{hex}'"),
+                        line,
+                        col,
+                    })?)
+                }
+                c if c.is_alphabetic() || c == '_' => {
+                    let mut word = String::from(c);
+                    while let Some(&c) = self.chars.peek() {
+                        if !(c.is_alphanumeric() || c == '_') {
+                            break;
+                        }
+                        word.push(c);
+                        self.bump();
+                    }
+                    TokenKind::Word(word)
+                }
+                other => {
+                    return Err(SyntaxError {
+                        message: format!("unexpected character '{other}'"),
+                        line,
+                        col,
+                    });
+                }
+            };
+            tokens.push(Token { kind, line, col });
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.chars.peek() == Some(&expected) {
+            self.bump();
+            return true;
+        }
+        false
+    }
+
+    fn peek_is(&mut self, pred: impl Fn(char) -> bool) -> bool {
+        self.chars.peek().is_some_and(|&c| pred(c))
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            while self.peek_is(char::is_whitespace) {
+                self.bump();
+            }
+            // `--` starts a comment that runs to the end of the line.
+            let mut lookahead = self.chars.clone();
+            if lookahead.next() == Some('-') && lookahead.next() == Some('-') {
+                while self.bump().is_some_and(|c| c != '\n') {}
+                continue;
+            }
+            return;
+        }
+    }
+
+    /// Reads the rest of a parameter starting with `prefix`.
+    fn parameter(&mut self, prefix: char, continues: impl Fn(char) -> bool) -> String {
+        let mut name = String::from(prefix);
+        while let Some(&c) = self.chars.peek() {
+            if !continues(c) {
+                break;
+            }
+            name.push(c);
+            self.bump();
+        }
+        name
+    }
+
+    /// Reads up to the closing `quote`. A doubled quote stands for itself.
+    fn quoted(&mut self, quote: char, line: usize, col: usize) -> Result<String, SyntaxError> {
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => {
+                    if !self.eat(quote) {
+                        return Ok(out);
+                    }
+                    out.push(quote);
+                }
+                Some(c) => out.push(c),
+                None => {
+                    return Err(SyntaxError {
+                        message: String::from("unterminated quoted string"),
+                        line,
+                        col,
+                    });
+                }
+            }
+        }
+    }
+
+    fn number(&mut self, first: char, line: usize, col: usize) -> Result<TokenKind, SyntaxError> {
+        let mut text = String::from(first);
+        let mut is_real = first == '.';
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_digit() {
+                text.push(c);
+            } else if c == '.' && !is_real {
+                is_real = true;
+                text.push(c);
+            } else if (c == 'e' || c == 'E') && !text.contains(['e', 'E']) {
+                is_real = true;
+                text.push(c);
+                self.bump();
+                if let Some(&sign) = self.chars.peek()
+                    && (sign == '+' || sign == '-')
+                {
+                    text.push(sign);
+                    self.bump();
+                }
+                continue;
+            } else {
+                break;
+            }
+            self.bump();
+        }
+
+        let invalid = || SyntaxError {
+            message: format!("invalid number '{text}'"),
+            line,
+            col,
+        };
+        if !is_real && let Ok(n) = text.parse() {
+            return Ok(TokenKind::Integer(n));
+        }
+        // Integers too long for 64 bits are reals, as in SQLite, but no
+        // number may be out of the range of a real.
+        let n: f64 = text.parse().map_err(|_| invalid())?;
+        if n.is_infinite() {
+            return Err(SyntaxError {
+                message: format!("number out of range '{text}'"),
+                line,
+                col,
+            });
+        }
+        Ok(TokenKind::Real(n))
+    }
+}
+
+/// Decodes an even number of hex digits.
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
@@ -0,0 +1,79 @@
+//! A small SQLite-like database engine that keeps each database in a single
+//! file of fixed-size pages.
+//!
+//! Everything goes through a [`Connection`]: [`Connection::execute`] runs
+//! statements that change the database, and [`Connection::query`] runs a
+//! `SELECT` and returns its [`Rows`]. Values from outside belong in the
+//! parameters of a [`Statement`] from [`Connection::prepare`], rather than
+//! in the SQL itself.
+//!
+//! ```no_run
+//! use rustqlite::{Connection, Value};
+//!
+//! # fn main() -> rustqlite::Result<()> {
+//! let mut conn = Connection::open("people.db")?;
+//! conn.execute("CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")?;
+//! conn.execute("INSERT INTO people (name) VALUES ('alice'), ('bob')")?;
+//!
+//! let rows = conn.query("SELECT * FROM people WHERE id = 2")?;
+//! assert_eq!(rows.columns(), ["id", "name"]);
+//! for row in &rows {
+//!     assert_eq!(row.value(1), Some(&Value::Text(String::from("bob"))));
+//! }
+//!
+//! let mut insert = conn.prepare("INSERT INTO people (name) VALUES (:name)")?;
+//! for name in ["carol", "o'brien"] {
+//!     insert.bind_text(1, name)?;
+//!     insert.step()?;
+//!     insert.reset();
+//! }
+//! drop(insert);
+//!
+//! let mut select = conn.prepare("SELECT * FROM people WHERE name = ?")?;
+//! select.bind_text(1, "o'brien")?;
+//! while let Some(row) = select.step()? {
+//!     let id: i64 = row.get(0)?;
+//!     assert_eq!(id, 4);
+//! }
+//! drop(select);
+//! conn.close()
+//! # }
+//! ```
+
+#![warn(missing_docs)]
+
+// mod btree;
+mod ast;
+mod connection;
+mod connection_test;
+mod crypto;
+mod cursor;
+mod database;
+mod error;
+mod eval;
+mod eval_test;
+mod frames;
+mod header;
+mod lexer;
+mod overflow;
+mod parser;
+mod parser_test;
+mod schema;
+mod schema_test;
+mod persistence;
+mod persistence_test;
+mod record;
+mod record_test;
+mod statement;
+mod statement_test;
+mod table;
+mod value;
+mod value_test;
+
+pub use connection::{Connection, Row, Rows, Statement};
+pub use error::{Constraint, Error, Limit, Object, Result};
+pub use header::DEFAULT_PAGE_SIZE;
+pub use lexer::SyntaxError;
+pub use persistence::{PagerConfig, PagerStats};
+pub use schema::CATALOG_TABLE_NAME;
+pub use value::{FromValue, Value};
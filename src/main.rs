@@ -1,22 +1,17 @@
-// mod btree;
-mod cursor;
-mod persistence;
-mod statement;
-mod statement_test;
-mod table;
-
-use crate::MetaCommandCode::MetaCommandUnknown;
-use crate::PrepareStatementCode::{
-    PrepareStatementFailure, PrepareStatementInsert, PrepareStatementSelect,
-};
-use crate::StatementCode::StatementSuccess;
-use crate::statement::{select, InsertError};
-use crate::table::{Table};
+use crate::MetaCommandCode::{MetaCommandSuccess, MetaCommandUnknown};
+use rustqlite::{CATALOG_TABLE_NAME, Connection, DEFAULT_PAGE_SIZE, PagerConfig, Result, Rows, Value};
+use std::fs;
 use std::io;
-use std::io::Error;
 use std::process::exit;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use clap::Parser;
-use crate::cursor::Cursor;
+
+/// The table the tutorial's shorthand works on, which every new database
+/// starts out with.
+const DEFAULT_TABLE_NAME: &str = "users";
+
+const DEFAULT_SCHEMA: &str =
+    "CREATE TABLE users (id INTEGER PRIMARY KEY, name VARCHAR(32), email VARCHAR(255))";
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -24,88 +19,217 @@ pub struct Args {
     /// Name of the database
     #[arg(short, long)]
     database: String,
-}
 
-enum StatementCode {
-    StatementSuccess,
-    StatementFailure,
+    /// Maximum number of bytes of the database file to memory-map for reads (0 disables mmap)
+    #[arg(long, default_value_t = 0)]
+    mmap_size: usize,
+
+    /// Page size in bytes for a newly created database (power of two, 512 to 65536)
+    #[arg(long, default_value_t = DEFAULT_PAGE_SIZE)]
+    page_size: usize,
+
+    /// Compress the pages of a newly created database (disables the mmap read path)
+    #[arg(long)]
+    compress: bool,
+
+    /// Encryption key; creates an encrypted database or opens one (can also be set with PRAGMA key)
+    #[arg(long)]
+    key: Option<String>,
 }
 
-enum PrepareStatementCode {
-    PrepareStatementSelect,
-    PrepareStatementInsert,
-    PrepareStatementFailure,
+impl Args {
+    fn pager_config(&self) -> PagerConfig {
+        let mut config = PagerConfig::default();
+        config.mmap_size = self.mmap_size;
+        config.page_size = self.page_size;
+        config.compress = self.compress;
+        config.key = self.key.clone();
+        config
+    }
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 enum MetaCommandCode {
     MetaCommandSuccess,
-    MetaCommandFailure,
     MetaCommandUnknown,
 }
 
-fn main() -> Result<(), Error> {
-    while true {
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+    let mut is_new = fs::metadata(&args.database).map_or(true, |metadata| metadata.len() == 0);
+    let conn = match Connection::open_with(&args.database, &args.pager_config()) {
+        Ok(conn) => conn,
+        Err(err) => {
+            println!("{err}");
+            exit(1)
+        }
+    };
+    let conn = Arc::new(Mutex::new(conn));
+
+    // The handler waits for a running statement to finish before closing.
+    let interrupted = Arc::clone(&conn);
+    ctrlc::set_handler(move || {
+        println!();
+        shut_down(&mut lock(&interrupted));
+    })
+    .map_err(io::Error::other)?;
+
+    loop {
         println!("----------------------------");
         let mut buffer = String::new();
-        io::stdin().read_line(&mut buffer)?;
-        let input = buffer.strip_suffix("\n").unwrap();
+        if io::stdin().read_line(&mut buffer)? == 0 {
+            shut_down(&mut lock(&conn));
+        }
+        let input = buffer.trim_end_matches(['\n', '\r']);
+        let mut conn = lock(&conn);
+        // The default table waits for the first statement that isn't a
+        // PRAGMA, so that `PRAGMA key` can still encrypt a new database.
+        if is_new && !is_pragma(input) {
+            is_new = false;
+            if let Err(err) = conn.execute(DEFAULT_SCHEMA) {
+                println!("{err}");
+            }
+        }
         if input.starts_with(".") {
-            let output = exec_meta_command(input)?;
-            println!("Executed meta command with result: {output:?}");
-        } else {
-            let output = prepare_statement(input)?;
-            exec_statement(input, output).expect("TODO: panic message");
+            match exec_meta_command(input, &mut conn) {
+                Ok(output) => println!("Executed meta command with result: {output:?}"),
+                Err(err) => println!("Error: {err}"),
+            }
+        } else if let Err(err) = exec_statement(input, &mut conn) {
+            println!("{err}");
+        }
+    }
+}
+
+fn is_pragma(input: &str) -> bool {
+    input.split_whitespace().next().is_some_and(|word| word.eq_ignore_ascii_case("pragma"))
+}
+
+fn lock(conn: &Mutex<Connection>) -> MutexGuard<'_, Connection> {
+    conn.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Closes the connection and exits, on `.exit`, end of input or Ctrl-C.
+fn shut_down(conn: &mut Connection) -> ! {
+    println!("Shutting down database.");
+    match conn.close() {
+        Ok(()) => {
+            println!("Flushed to disk complete!");
+            exit(0)
+        }
+        Err(err) => {
+            println!("Error flushing on exit: {err}");
+            exit(1)
         }
     }
-    Ok(())
 }
 
-fn exec_meta_command(cmd: &str) -> Result<MetaCommandCode, Error> {
+fn exec_meta_command(cmd: &str, conn: &mut Connection) -> Result<MetaCommandCode> {
     if cmd == ".exit" {
-        println!("Shutting down database.");
-        let args = Args::parse();
-        let result = Cursor::new(&mut Table::db_open(&args.database)?).table.db_close();
-        match result {
-            Ok(res) => println!("Flushed to disk complete!"),
-            Err(err) => {}
+        shut_down(conn);
+    }
+    if cmd == ".dbinfo" {
+        print_dbinfo(conn)?;
+        return Ok(MetaCommandSuccess);
+    }
+    if cmd == ".tables" {
+        let catalog = conn.query(&format!("SELECT * FROM {CATALOG_TABLE_NAME}"))?;
+        let names: Vec<&str> = catalog
+            .iter()
+            .filter_map(|row| match row.values() {
+                [Value::Text(kind), Value::Text(name), ..] if kind == "table" => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        println!("{}", names.join(" "));
+        return Ok(MetaCommandSuccess);
+    }
+    if cmd == ".schema" || cmd.starts_with(".schema ") {
+        let filter = cmd[".schema".len()..].trim();
+        let catalog = conn.query(&format!("SELECT * FROM {CATALOG_TABLE_NAME}"))?;
+        for row in &catalog {
+            if let [_, _, Value::Text(table_name), _, Value::Text(sql)] = row.values()
+                && (filter.is_empty() || table_name.eq_ignore_ascii_case(filter))
+            {
+                println!("{sql};");
+            }
         }
-        exit(0);
+        return Ok(MetaCommandSuccess);
     }
     Ok(MetaCommandUnknown)
 }
 
-fn prepare_statement(cmd: &str) -> Result<PrepareStatementCode, Error> {
-    if cmd.starts_with("select") && cmd.len() > 6 {
-        return Ok(PrepareStatementSelect);
-    } else if cmd.starts_with("insert") && cmd.len() > 6 {
-        return Ok(PrepareStatementInsert);
+fn print_dbinfo(conn: &mut Connection) -> Result<()> {
+    let stats = conn.stats()?;
+    let tables = conn.query(&format!("SELECT * FROM {CATALOG_TABLE_NAME} WHERE type = 'table'"))?;
+    println!("page size: {}", stats.page_size);
+    println!("page count: {}", stats.page_count);
+    println!("free pages: {}", stats.free_pages);
+    println!("tables: {}", tables.len());
+    println!("compression: {}", if stats.compressed { "lz4" } else { "off" });
+    println!("encryption: {}", if stats.encrypted { "chacha20-poly1305" } else { "off" });
+    println!("page bytes: {} stored / {} uncompressed", stats.stored_bytes, stats.logical_bytes);
+    if stats.compressed && stats.stored_bytes > 0 {
+        let ratio = stats.logical_bytes as f64 / stats.stored_bytes as f64;
+        println!("compression ratio: {ratio:.2}:1");
     }
-    Ok(PrepareStatementFailure)
+    Ok(())
 }
 
-fn exec_statement(cmd: &str, statement_type: PrepareStatementCode) -> Result<StatementCode, Error> {
-    let args = Args::parse();
-    let mut table = Table::db_open(&args.database)?;
-    let curr = &mut Cursor::new(&mut table);
-    match statement_type {
-        PrepareStatementSelect => {
-            let _ = select(curr, cmd);
+/// Runs a statement, printing the rows of a query and the row count of an
+/// update or delete. Besides SQL this takes the tutorial's shorthand,
+/// `insert <id> <name> <email>` and `select [<id>]`, on the default table.
+fn exec_statement(input: &str, conn: &mut Connection) -> Result<()> {
+    let words: Vec<&str> = input.split_ascii_whitespace().collect();
+    match words[..] {
+        ["insert", ref args @ ..] if args.first().is_some_and(|word| !word.eq_ignore_ascii_case("into")) => {
+            return insert_shorthand(conn, args);
         }
-        PrepareStatementInsert => {
-            let _ = statement::insert(curr, cmd);
-            let result = curr.table.db_close();
-            match result {
-                Ok(_) => {},
-                Err(err) => {
-                    println!("Error flushing after insert!");
-                    return Err(err)
-                }
-            }
+        ["select"] => return exec_statement(&format!("SELECT * FROM {DEFAULT_TABLE_NAME}"), conn),
+        ["select", id] if id.parse::<i64>().is_ok() => {
+            return exec_statement(&format!("SELECT * FROM {DEFAULT_TABLE_NAME} WHERE id = {id}"), conn);
+        }
+        _ => {}
+    }
+
+    let keyword = input.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+    match keyword.as_str() {
+        "select" => print_rows(&conn.query(input)?),
+        "update" | "delete" => println!("Rows affected: {}", conn.execute(input)?),
+        _ => {
+            conn.execute(input)?;
         }
-        PrepareStatementFailure => {
-            println!("Statement failed to be classified")
+    }
+    Ok(())
+}
+
+/// Inserts a row into the default table, taking each word as an integer
+/// if it reads as one and as text otherwise.
+fn insert_shorthand(conn: &mut Connection, words: &[&str]) -> Result<()> {
+    let placeholders = vec!["?"; words.len()].join(", ");
+    let mut insert = conn.prepare(&format!("INSERT INTO {DEFAULT_TABLE_NAME} VALUES ({placeholders})"))?;
+    for (index, word) in words.iter().enumerate() {
+        match word.parse::<i64>() {
+            Ok(n) => insert.bind_int(index + 1, n)?,
+            Err(_) => insert.bind_text(index + 1, word)?,
         }
     }
-    Ok(StatementSuccess)
+    insert.step()?;
+    Ok(())
+}
+
+fn print_rows(rows: &Rows) {
+    if rows.is_empty() && rows.is_rowid_lookup() {
+        println!("Row does not exist.");
+    }
+    for row in rows {
+        let fields: Vec<String> = rows
+            .columns()
+            .iter()
+            .zip(row.values())
+            .map(|(column, value)| format!("{column}: {value}"))
+            .collect();
+        println!("{}", fields.join("; "));
+    }
 }
@@ -0,0 +1,92 @@
+use crate::error::{Error, Limit, Result};
+use crate::table::Table;
+
+/// Every overflow page starts with the page number of the next page in its
+/// chain; `0` ends the chain. The rest of the page is payload.
+pub const OVERFLOW_NEXT_SIZE: usize = size_of::<u32>();
+
+/// Writes `bytes` to an overflow chain and returns its first page, or `0`
+/// when there is nothing to spill. Pages of the chain starting at
+/// `reuse_from` are overwritten before new pages are allocated, and the
+/// ones left over go back to the freelist.
+pub fn write_chain(table: &mut Table, bytes: &[u8], reuse_from: u32) -> Result<u32> {
+    let payload_size = table.pager.page_size() - OVERFLOW_NEXT_SIZE;
+    let mut reuse = reuse_from as usize;
+    let mut first = 0;
+    let mut prev: Option<usize> = None;
+
+    for chunk in bytes.chunks(payload_size) {
+        let page_num = if reuse != 0 {
+            let page_num = reuse;
+            reuse = next_page(table, page_num)?;
+            page_num
+        } else {
+            table.pager.allocate_page().ok_or(Limit::TableFull)?
+        };
+
+        match prev {
+            Some(prev) => set_next_page(table, prev, page_num)?,
+            None => first = page_num,
+        }
+
+        let page = table.get_page(page_num)?;
+        page.fill(0);
+        page[OVERFLOW_NEXT_SIZE..OVERFLOW_NEXT_SIZE + chunk.len()].copy_from_slice(chunk);
+        prev = Some(page_num);
+    }
+
+    // A shorter record leaves the tail of the old chain unused.
+    if reuse != 0 {
+        for page_num in chain_pages(table, reuse as u32)? {
+            table.pager.free_page(page_num);
+        }
+    }
+    Ok(first as u32)
+}
+
+/// The pages of the overflow chain starting at `first_page`, in order.
+pub fn chain_pages(table: &mut Table, first_page: u32) -> Result<Vec<usize>> {
+    let mut pages = Vec::new();
+    let mut page_num = first_page as usize;
+    while page_num != 0 {
+        if pages.contains(&page_num) {
+            return Err(Error::corrupt(format!("overflow chain from page {first_page} loops")));
+        }
+        pages.push(page_num);
+        page_num = next_page(table, page_num)?;
+    }
+    Ok(pages)
+}
+
+/// Reads `len` bytes back from the overflow chain starting at `first_page`.
+pub fn read_chain(table: &mut Table, first_page: u32, len: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(len);
+    let mut page_num = first_page as usize;
+
+    while out.len() < len {
+        if page_num == 0 {
+            return Err(Error::corrupt(format!("overflow chain from page {first_page} ended early")));
+        }
+        let page = table.read_page(page_num)?;
+        let take = (len - out.len()).min(page.len() - OVERFLOW_NEXT_SIZE);
+        out.extend_from_slice(&page[OVERFLOW_NEXT_SIZE..OVERFLOW_NEXT_SIZE + take]);
+        page_num = read_next(page);
+    }
+
+    Ok(out)
+}
+
+fn next_page(table: &mut Table, page_num: usize) -> Result<usize> {
+    let page = table.read_page(page_num)?;
+    Ok(read_next(page))
+}
+
+fn set_next_page(table: &mut Table, page_num: usize, next: usize) -> Result<()> {
+    let page = table.get_page(page_num)?;
+    page[..OVERFLOW_NEXT_SIZE].copy_from_slice(&(next as u32).to_le_bytes());
+    Ok(())
+}
+
+fn read_next(page: &[u8]) -> usize {
+    u32::from_le_bytes(page[..OVERFLOW_NEXT_SIZE].try_into().unwrap()) as usize
+}
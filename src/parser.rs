@@ -0,0 +1,765 @@
+use crate::ast::{
+    AlterAction, AlterTable, BinaryOp, ColumnConstraint, ColumnDef, CreateTable, Delete, Expr, Insert,
+    Literal, ResultColumn, Select, Statement, UnaryOp, Update,
+};
+use crate::lexer::{tokenize, SyntaxError, Token, TokenKind};
+
+/// Words that can't be used as bare identifiers.
+const RESERVED: &[&str] = &[
+    "ALTER", "AND", "BEGIN", "BETWEEN", "CHECK", "COLUMN", "COMMIT", "CREATE", "DEFAULT", "DELETE", "DROP",
+    "FROM", "IN", "INDEX", "INSERT", "INTO", "IS", "KEY", "NOT", "NULL", "OR", "PRIMARY", "ROLLBACK",
+    "SELECT", "SET", "TABLE", "TO", "UNIQUE", "UPDATE", "VALUES", "WHERE",
+];
+
+/// How deeply expressions may nest, so that neither parsing nor evaluating
+/// one can run out of stack. Parentheses and unary operators count as a
+/// level each, as does every operator of a chain such as `1 + 2 + 3`.
+const MAX_EXPR_DEPTH: usize = 100;
+
+/// The highest index a parameter may have, as in SQLite.
+const MAX_PARAMETERS: usize = 32766;
+
+/// Parses one statement, optionally followed by a `;`.
+pub fn parse(input: &str) -> Result<Statement, SyntaxError> {
+    parse_with_parameters(input).map(|(statement, _)| statement)
+}
+
+/// Parses one statement like `parse`, also returning the name of each of
+/// its parameters by index less one: `None` for one written as a bare `?`.
+pub fn parse_with_parameters(input: &str) -> Result<(Statement, Vec<Option<String>>), SyntaxError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+        depth: 0,
+        parameters: vec![],
+        in_column_def: false,
+    };
+    let statement = parser.statement()?;
+    parser.eat(&TokenKind::Semicolon);
+    if parser.peek().kind != TokenKind::Eof {
+        return Err(parser.unexpected("end of statement"));
+    }
+    Ok((statement, parser.parameters))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// How many expressions the one being parsed is nested in.
+    depth: usize,
+    /// The parameters seen so far, named as in `parse_with_parameters`.
+    parameters: Vec<Option<String>>,
+    /// Set while parsing a column definition, whose defaults and checks are
+    /// kept in the catalog and so can't refer to parameters.
+    in_column_def: bool,
+}
+
+impl Parser {
+    fn statement(&mut self) -> Result<Statement, SyntaxError> {
+        if self.eat_keyword("SELECT") {
+            return self.select().map(Statement::Select);
+        }
+        if self.eat_keyword("INSERT") {
+            return self.insert().map(Statement::Insert);
+        }
+        if self.eat_keyword("UPDATE") {
+            return self.update().map(Statement::Update);
+        }
+        if self.eat_keyword("DELETE") {
+            return self.delete().map(Statement::Delete);
+        }
+        if self.eat_keyword("CREATE") {
+            self.expect_keyword("TABLE")?;
+            return self.create_table().map(Statement::CreateTable);
+        }
+        if self.eat_keyword("ALTER") {
+            self.expect_keyword("TABLE")?;
+            return self.alter_table().map(Statement::AlterTable);
+        }
+        if self.eat_keyword("DROP") {
+            let index = self.eat_keyword("INDEX");
+            if !index {
+                self.expect_keyword("TABLE")?;
+            }
+            let if_exists = self.if_exists()?;
+            let name = self.identifier()?;
+            return Ok(match index {
+                true => Statement::DropIndex { name, if_exists },
+                false => Statement::DropTable { name, if_exists },
+            });
+        }
+        if self.eat_keyword("BEGIN") {
+            self.eat_keyword("TRANSACTION");
+            return Ok(Statement::Begin);
+        }
+        if self.eat_keyword("COMMIT") || self.eat_keyword("END") {
+            self.eat_keyword("TRANSACTION");
+            return Ok(Statement::Commit);
+        }
+        if self.eat_keyword("ROLLBACK") {
+            self.eat_keyword("TRANSACTION");
+            if self.eat_keyword("TO") {
+                self.eat_keyword("SAVEPOINT");
+                return self.identifier().map(Statement::RollbackTo);
+            }
+            return Ok(Statement::Rollback);
+        }
+        if self.eat_keyword("SAVEPOINT") {
+            return self.identifier().map(Statement::Savepoint);
+        }
+        if self.eat_keyword("RELEASE") {
+            self.eat_keyword("SAVEPOINT");
+            return self.identifier().map(Statement::Release);
+        }
+        if self.eat_keyword("PRAGMA") {
+            return self.pragma();
+        }
+        Err(self.unexpected("a statement"))
+    }
+
+    fn select(&mut self) -> Result<Select, SyntaxError> {
+        let mut columns = Vec::new();
+        loop {
+            if self.eat(&TokenKind::Star) {
+                columns.push(ResultColumn::Star);
+            } else {
+                columns.push(ResultColumn::Expr(self.expr()?));
+            }
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        let from = if self.eat_keyword("FROM") {
+            Some(self.identifier()?)
+        } else {
+            None
+        };
+        let where_clause = self.where_clause()?;
+        Ok(Select {
+            columns,
+            from,
+            where_clause,
+        })
+    }
+
+    fn insert(&mut self) -> Result<Insert, SyntaxError> {
+        self.expect_keyword("INTO")?;
+        let table = self.identifier()?;
+
+        let mut columns = Vec::new();
+        if self.eat(&TokenKind::LParen) {
+            loop {
+                columns.push(self.identifier()?);
+                if !self.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.expect(&TokenKind::RParen, "')'")?;
+        }
+
+        self.expect_keyword("VALUES")?;
+        let mut values = Vec::new();
+        loop {
+            self.expect(&TokenKind::LParen, "'('")?;
+            values.push(self.expr_list()?);
+            self.expect(&TokenKind::RParen, "')'")?;
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+
+        Ok(Insert {
+            table,
+            columns,
+            values,
+        })
+    }
+
+    fn update(&mut self) -> Result<Update, SyntaxError> {
+        let table = self.identifier()?;
+        self.expect_keyword("SET")?;
+        let mut assignments = Vec::new();
+        loop {
+            let column = self.identifier()?;
+            self.expect(&TokenKind::Eq, "'='")?;
+            assignments.push((column, self.expr()?));
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        let where_clause = self.where_clause()?;
+        Ok(Update {
+            table,
+            assignments,
+            where_clause,
+        })
+    }
+
+    fn delete(&mut self) -> Result<Delete, SyntaxError> {
+        self.expect_keyword("FROM")?;
+        let table = self.identifier()?;
+        let where_clause = self.where_clause()?;
+        Ok(Delete {
+            table,
+            where_clause,
+        })
+    }
+
+    fn create_table(&mut self) -> Result<CreateTable, SyntaxError> {
+        let if_not_exists = if self.eat_keyword("IF") {
+            self.expect_keyword("NOT")?;
+            self.expect_keyword("EXISTS")?;
+            true
+        } else {
+            false
+        };
+        let name = self.identifier()?;
+
+        self.expect(&TokenKind::LParen, "'('")?;
+        let mut columns = Vec::new();
+        loop {
+            columns.push(self.column_def()?);
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(&TokenKind::RParen, "')'")?;
+
+        Ok(CreateTable {
+            name,
+            if_not_exists,
+            columns,
+        })
+    }
+
+    fn alter_table(&mut self) -> Result<AlterTable, SyntaxError> {
+        let table = self.identifier()?;
+        let action = if self.eat_keyword("ADD") {
+            self.eat_keyword("COLUMN");
+            AlterAction::AddColumn(self.column_def()?)
+        } else if self.eat_keyword("RENAME") {
+            if self.eat_keyword("TO") {
+                AlterAction::RenameTable(self.identifier()?)
+            } else {
+                self.eat_keyword("COLUMN");
+                let from = self.identifier()?;
+                self.expect_keyword("TO")?;
+                AlterAction::RenameColumn { from, to: self.identifier()? }
+            }
+        } else if self.eat_keyword("DROP") {
+            self.eat_keyword("COLUMN");
+            AlterAction::DropColumn(self.identifier()?)
+        } else {
+            return Err(self.unexpected("ADD, RENAME or DROP"));
+        };
+        Ok(AlterTable { table, action })
+    }
+
+    fn column_def(&mut self) -> Result<ColumnDef, SyntaxError> {
+        self.in_column_def = true;
+        let column = self.column_def_inner();
+        self.in_column_def = false;
+        column
+    }
+
+    fn column_def_inner(&mut self) -> Result<ColumnDef, SyntaxError> {
+        let name = self.identifier()?;
+
+        // A type name is one or more words, optionally followed by a size like `(255)`.
+        let mut type_words = Vec::new();
+        while let TokenKind::Word(word) = &self.peek().kind {
+            if is_constraint_start(word) {
+                break;
+            }
+            type_words.push(word.to_ascii_uppercase());
+            self.advance();
+        }
+        let mut type_name = (!type_words.is_empty()).then(|| type_words.join(" "));
+        if type_name.is_some() && self.eat(&TokenKind::LParen) {
+            let mut size = Vec::new();
+            loop {
+                size.push(self.signed_integer()?.to_string());
+                if !self.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.expect(&TokenKind::RParen, "')'")?;
+            type_name = type_name.map(|name| format!("{name}({})", size.join(",")));
+        }
+
+        let mut constraints = Vec::new();
+        loop {
+            if self.eat_keyword("CONSTRAINT") {
+                self.identifier()?;
+            }
+            if self.eat_keyword("PRIMARY") {
+                self.expect_keyword("KEY")?;
+                if !self.eat_keyword("ASC") {
+                    self.eat_keyword("DESC");
+                }
+                let autoincrement = self.eat_keyword("AUTOINCREMENT");
+                constraints.push(ColumnConstraint::PrimaryKey { autoincrement });
+            } else if self.eat_keyword("NOT") {
+                self.expect_keyword("NULL")?;
+                constraints.push(ColumnConstraint::NotNull);
+            } else if self.eat_keyword("UNIQUE") {
+                constraints.push(ColumnConstraint::Unique);
+            } else if self.eat_keyword("DEFAULT") {
+                let default = if self.eat(&TokenKind::LParen) {
+                    let expr = self.expr()?;
+                    self.expect(&TokenKind::RParen, "')'")?;
+                    expr
+                } else {
+                    self.unary()?
+                };
+                constraints.push(ColumnConstraint::Default(default));
+            } else if self.eat_keyword("CHECK") {
+                self.expect(&TokenKind::LParen, "'('")?;
+                let check = self.expr()?;
+                self.expect(&TokenKind::RParen, "')'")?;
+                constraints.push(ColumnConstraint::Check(check));
+            } else {
+                break;
+            }
+        }
+
+        Ok(ColumnDef {
+            name,
+            type_name,
+            constraints,
+        })
+    }
+
+    fn pragma(&mut self) -> Result<Statement, SyntaxError> {
+        // Pragma names aren't reserved here, `key` among them.
+        let name = match &self.peek().kind {
+            TokenKind::Word(word) => {
+                let word = word.to_ascii_lowercase();
+                self.advance();
+                word
+            }
+            _ => self.identifier()?.to_ascii_lowercase(),
+        };
+        let value = if self.eat(&TokenKind::Eq) {
+            Some(self.pragma_value()?)
+        } else if self.eat(&TokenKind::LParen) {
+            let value = self.pragma_value()?;
+            self.expect(&TokenKind::RParen, "')'")?;
+            Some(value)
+        } else {
+            None
+        };
+        Ok(Statement::Pragma { name, value })
+    }
+
+    fn pragma_value(&mut self) -> Result<Literal, SyntaxError> {
+        if let TokenKind::Word(word) = &self.peek().kind {
+            let word = word.clone();
+            self.advance();
+            return Ok(Literal::String(word));
+        }
+        match self.unary()? {
+            Expr::Literal(literal) => Ok(literal),
+            _ => Err(self.unexpected("a literal value")),
+        }
+    }
+
+    fn where_clause(&mut self) -> Result<Option<Expr>, SyntaxError> {
+        if self.eat_keyword("WHERE") {
+            return self.expr().map(Some);
+        }
+        Ok(None)
+    }
+
+    fn if_exists(&mut self) -> Result<bool, SyntaxError> {
+        if self.eat_keyword("IF") {
+            self.expect_keyword("EXISTS")?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn expr_list(&mut self) -> Result<Vec<Expr>, SyntaxError> {
+        let mut exprs = vec![self.expr()?];
+        while self.eat(&TokenKind::Comma) {
+            exprs.push(self.expr()?);
+        }
+        Ok(exprs)
+    }
+
+    fn expr(&mut self) -> Result<Expr, SyntaxError> {
+        self.nested(Self::or)
+    }
+
+    /// Parses an expression one level deeper than the current one.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr, SyntaxError>) -> Result<Expr, SyntaxError> {
+        if self.depth == MAX_EXPR_DEPTH {
+            return Err(self.too_deep());
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    /// Passes `expr` on unless it nests deeper than `MAX_EXPR_DEPTH`. Chains
+    /// of operators are parsed in a loop rather than by recursion, so their
+    /// depth is checked as the tree is built.
+    fn within_depth(&self, expr: Expr) -> Result<Expr, SyntaxError> {
+        match height(&expr) > MAX_EXPR_DEPTH {
+            true => Err(self.too_deep()),
+            false => Ok(expr),
+        }
+    }
+
+    /// Applies `op` to `left` and the operand `right` parses next.
+    fn binary(
+        &mut self,
+        op: BinaryOp,
+        left: Expr,
+        right: fn(&mut Self) -> Result<Expr, SyntaxError>,
+    ) -> Result<Expr, SyntaxError> {
+        let right = right(self)?;
+        self.within_depth(Expr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+    }
+
+    fn or(&mut self) -> Result<Expr, SyntaxError> {
+        let mut left = self.and()?;
+        while self.eat_keyword("OR") {
+            left = self.binary(BinaryOp::Or, left, Self::and)?;
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, SyntaxError> {
+        let mut left = self.not()?;
+        while self.eat_keyword("AND") {
+            left = self.binary(BinaryOp::And, left, Self::not)?;
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, SyntaxError> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Unary {
+                op: UnaryOp::Not,
+                expr: Box::new(self.nested(Self::not)?),
+            });
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, SyntaxError> {
+        let mut left = self.additive()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Eq => BinaryOp::Eq,
+                TokenKind::NotEq => BinaryOp::NotEq,
+                TokenKind::Lt => BinaryOp::Lt,
+                TokenKind::LtEq => BinaryOp::LtEq,
+                TokenKind::Gt => BinaryOp::Gt,
+                TokenKind::GtEq => BinaryOp::GtEq,
+                _ if self.at_postfix_predicate() => {
+                    left = self.postfix_predicate(left)?;
+                    left = self.within_depth(left)?;
+                    continue;
+                }
+                _ => return Ok(left),
+            };
+            self.advance();
+            left = self.binary(op, left, Self::additive)?;
+        }
+    }
+
+    fn at_postfix_predicate(&self) -> bool {
+        let is_keyword = |offset: usize, keywords: &[&str]| {
+            let token = &self.tokens[(self.pos + offset).min(self.tokens.len() - 1)];
+            matches!(&token.kind, TokenKind::Word(word)
+                if keywords.iter().any(|keyword| keyword.eq_ignore_ascii_case(word)))
+        };
+        is_keyword(0, &["IS", "IN", "BETWEEN"]) || (is_keyword(0, &["NOT"]) && is_keyword(1, &["IN", "BETWEEN"]))
+    }
+
+    /// Parses `IS [NOT] NULL`, `[NOT] IN (...)` or `[NOT] BETWEEN .. AND ..`
+    /// applied to `left`.
+    fn postfix_predicate(&mut self, left: Expr) -> Result<Expr, SyntaxError> {
+        let expr = Box::new(left);
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull { expr, negated });
+        }
+
+        let negated = self.eat_keyword("NOT");
+        if self.eat_keyword("IN") {
+            self.expect(&TokenKind::LParen, "'('")?;
+            let list = self.expr_list()?;
+            self.expect(&TokenKind::RParen, "')'")?;
+            return Ok(Expr::In { expr, list, negated });
+        }
+        self.expect_keyword("BETWEEN")?;
+        // The bounds can't contain a bare AND, which separates them.
+        let low = Box::new(self.additive()?);
+        self.expect_keyword("AND")?;
+        let high = Box::new(self.additive()?);
+        Ok(Expr::Between { expr, low, high, negated })
+    }
+
+    fn additive(&mut self) -> Result<Expr, SyntaxError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Plus => BinaryOp::Add,
+                TokenKind::Minus => BinaryOp::Subtract,
+                _ => return Ok(left),
+            };
+            self.advance();
+            left = self.binary(op, left, Self::multiplicative)?;
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, SyntaxError> {
+        let mut left = self.concat()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Star => BinaryOp::Multiply,
+                TokenKind::Slash => BinaryOp::Divide,
+                TokenKind::Percent => BinaryOp::Modulo,
+                _ => return Ok(left),
+            };
+            self.advance();
+            left = self.binary(op, left, Self::concat)?;
+        }
+    }
+
+    fn concat(&mut self) -> Result<Expr, SyntaxError> {
+        let mut left = self.unary()?;
+        while self.eat(&TokenKind::Concat) {
+            left = self.binary(BinaryOp::Concat, left, Self::unary)?;
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, SyntaxError> {
+        if self.eat(&TokenKind::Minus) {
+            // The one integer whose magnitude only fits negated.
+            if self.peek().kind == TokenKind::Integer(i64::MIN.unsigned_abs()) {
+                self.advance();
+                return Ok(Expr::Literal(Literal::Integer(i64::MIN)));
+            }
+            return Ok(match self.nested(Self::unary)? {
+                Expr::Literal(Literal::Integer(n)) => {
+                    Expr::Literal(n.checked_neg().map_or(Literal::Real(-(n as f64)), Literal::Integer))
+                }
+                Expr::Literal(Literal::Real(n)) => Expr::Literal(Literal::Real(-n)),
+                expr => Expr::Unary {
+                    op: UnaryOp::Negate,
+                    expr: Box::new(expr),
+                },
+            });
+        }
+        if self.eat(&TokenKind::Plus) {
+            return self.nested(Self::unary);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, SyntaxError> {
+        let token = self.peek().clone();
+        let expr = match token.kind {
+            TokenKind::Integer(n) => Expr::Literal(i64::try_from(n).map_or(Literal::Real(n as f64), Literal::Integer)),
+            TokenKind::Real(n) => Expr::Literal(Literal::Real(n)),
+            TokenKind::String(s) => Expr::Literal(Literal::String(s)),
+            TokenKind::Blob(bytes) => Expr::Literal(Literal::Blob(bytes)),
+            TokenKind::Parameter(ref name) => Expr::Parameter(self.parameter(name)?),
+            TokenKind::LParen => {
+                self.advance();
+                let expr = self.expr()?;
+                self.expect(&TokenKind::RParen, "')'")?;
+                return Ok(expr);
+            }
+            TokenKind::Word(ref word) if word.eq_ignore_ascii_case("NULL") => {
+                Expr::Literal(Literal::Null)
+            }
+            TokenKind::Word(_) | TokenKind::QuotedIdent(_) => {
+                let mut name = self.identifier()?;
+                if self.eat(&TokenKind::LParen) {
+                    let mut args = Vec::new();
+                    if !self.eat(&TokenKind::RParen) {
+                        loop {
+                            args.push(self.expr()?);
+                            if !self.eat(&TokenKind::Comma) {
+                                break;
+                            }
+                        }
+                        self.expect(&TokenKind::RParen, "')'")?;
+                    }
+                    return Ok(Expr::Function { name, args });
+                }
+                // `table.column` refers to the column; there is only ever one table in scope.
+                if self.eat(&TokenKind::Dot) {
+                    name = self.identifier()?;
+                }
+                return Ok(Expr::Column(name));
+            }
+            _ => return Err(self.unexpected("an expression")),
+        };
+        self.advance();
+        Ok(expr)
+    }
+
+    /// Gives the parameter `name` its index. A bare `?` takes the one after
+    /// the highest so far, and every `:name` written alike shares one.
+    fn parameter(&mut self, name: &str) -> Result<usize, SyntaxError> {
+        if self.in_column_def {
+            return Err(self.unexpected("a value that isn't a parameter"));
+        }
+        let index = match name.strip_prefix('?') {
+            Some("") => {
+                self.parameters.push(None);
+                return self.parameter_index(self.parameters.len());
+            }
+            Some(digits) => digits.parse().unwrap_or(0),
+            None => match self.parameters.iter().position(|known| known.as_deref() == Some(name)) {
+                Some(position) => position + 1,
+                None => self.parameters.len() + 1,
+            },
+        };
+        let index = self.parameter_index(index)?;
+        if self.parameters.len() < index {
+            self.parameters.resize(index, None);
+        }
+        // `?NNN` is named by what it's written as, like `:name`.
+        self.parameters[index - 1].get_or_insert_with(|| name.to_string());
+        Ok(index)
+    }
+
+    fn parameter_index(&self, index: usize) -> Result<usize, SyntaxError> {
+        if !(1..=MAX_PARAMETERS).contains(&index) {
+            let token = self.peek();
+            return Err(SyntaxError {
+                message: format!("parameter index must be between 1 and {MAX_PARAMETERS}"),
+                line: token.line,
+                col: token.col,
+            });
+        }
+        Ok(index)
+    }
+
+    fn signed_integer(&mut self) -> Result<i64, SyntaxError> {
+        let negative = self.eat(&TokenKind::Minus);
+        match self.peek().kind {
+            TokenKind::Integer(n) => {
+                let n = if negative { -i128::from(n) } else { i128::from(n) };
+                let n = i64::try_from(n).map_err(|_| self.unexpected("a 64-bit integer"))?;
+                self.advance();
+                Ok(n)
+            }
+            _ => Err(self.unexpected("an integer")),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, SyntaxError> {
+        match &self.peek().kind {
+            TokenKind::Word(word) if !is_reserved(word) => {
+                let word = word.clone();
+                self.advance();
+                Ok(word)
+            }
+            TokenKind::QuotedIdent(name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) {
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if &self.peek().kind == kind {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, kind: &TokenKind, what: &str) -> Result<(), SyntaxError> {
+        if self.eat(kind) {
+            return Ok(());
+        }
+        Err(self.unexpected(what))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if let TokenKind::Word(word) = &self.peek().kind
+            && word.eq_ignore_ascii_case(keyword)
+        {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), SyntaxError> {
+        if self.eat_keyword(keyword) {
+            return Ok(());
+        }
+        Err(self.unexpected(keyword))
+    }
+
+    fn too_deep(&self) -> SyntaxError {
+        let token = self.peek();
+        SyntaxError {
+            message: format!("expression is nested more than {MAX_EXPR_DEPTH} levels deep"),
+            line: token.line,
+            col: token.col,
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> SyntaxError {
+        let token = self.peek();
+        SyntaxError {
+            message: format!("expected {expected}, found {}", token.kind),
+            line: token.line,
+            col: token.col,
+        }
+    }
+}
+
+/// The number of levels in the tree of `expr`, `1` for a leaf.
+fn height(expr: &Expr) -> usize {
+    let children: Vec<&Expr> = match expr {
+        Expr::Literal(_) | Expr::Column(_) | Expr::Parameter(_) => vec![],
+        Expr::Unary { expr, .. } | Expr::IsNull { expr, .. } => vec![expr],
+        Expr::Binary { left, right, .. } => vec![left, right],
+        Expr::In { expr, list, .. } => std::iter::once(&**expr).chain(list).collect(),
+        Expr::Between { expr, low, high, .. } => vec![expr, low, high],
+        Expr::Function { args, .. } => args.iter().collect(),
+    };
+    1 + children.into_iter().map(height).max().unwrap_or(0)
+}
+
+pub(crate) fn is_reserved(word: &str) -> bool {
+    RESERVED.iter().any(|reserved| reserved.eq_ignore_ascii_case(word))
+}
+
+fn is_constraint_start(word: &str) -> bool {
+    ["CONSTRAINT", "PRIMARY", "NOT", "UNIQUE", "DEFAULT", "CHECK"]
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
@@ -0,0 +1,165 @@
+#[cfg(test)]
+mod tests {
+    use crate::ast::{
+        AlterAction, BinaryOp, ColumnConstraint, Expr, Literal, ResultColumn, Select, Statement, UnaryOp,
+    };
+    use crate::parser::{parse, parse_with_parameters};
+
+    #[test]
+    fn parses_select_with_where_in_any_keyword_case() {
+        let statement = parse("sElEcT * FrOm users WHERE NOT id = 3 AND name <> 'bob';").unwrap();
+        let Statement::Select(select) = statement else {
+            panic!("expected a select");
+        };
+        assert_eq!(select.columns, vec![ResultColumn::Star]);
+        assert_eq!(select.from.as_deref(), Some("users"));
+        let Some(Expr::Binary { op: BinaryOp::And, left, .. }) = select.where_clause else {
+            panic!("expected an AND");
+        };
+        assert!(matches!(*left, Expr::Unary { op: UnaryOp::Not, .. }));
+    }
+
+    #[test]
+    fn parses_quoted_strings_with_commas_and_escaped_quotes() {
+        let statement = parse("INSERT INTO users VALUES (1, 'O''Brien, Pat', \"x\")").unwrap();
+        let Statement::Insert(insert) = statement else {
+            panic!("expected an insert");
+        };
+        assert_eq!(insert.values[0][1], Expr::Literal(Literal::String(String::from("O'Brien, Pat"))));
+        assert_eq!(insert.values[0][2], Expr::Column(String::from("x")));
+    }
+
+    #[test]
+    fn parses_create_table_with_constraints() {
+        let statement = parse(
+            "CREATE TABLE t (id INTEGER PRIMARY KEY AUTOINCREMENT, name VARCHAR(32) NOT NULL DEFAULT 'anon', age INT CHECK (age >= 0))",
+        )
+        .unwrap();
+        let Statement::CreateTable(create) = statement else {
+            panic!("expected a create table");
+        };
+        assert_eq!(create.columns.len(), 3);
+        assert_eq!(create.columns[1].type_name.as_deref(), Some("VARCHAR(32)"));
+        assert_eq!(
+            create.columns[0].constraints,
+            vec![ColumnConstraint::PrimaryKey { autoincrement: true }]
+        );
+        assert_eq!(create.columns[1].constraints.len(), 2);
+    }
+
+    #[test]
+    fn parses_each_form_of_alter_table() {
+        let action = |sql| match parse(sql).unwrap() {
+            Statement::AlterTable(alter) => alter.action,
+            other => panic!("expected an alter table, got {other:?}"),
+        };
+        assert!(matches!(action("ALTER TABLE t ADD COLUMN c TEXT DEFAULT 'x'"), AlterAction::AddColumn(def) if def.name == "c"));
+        assert!(matches!(action("ALTER TABLE t ADD c"), AlterAction::AddColumn(_)));
+        assert_eq!(
+            action("ALTER TABLE t RENAME COLUMN a TO b"),
+            AlterAction::RenameColumn { from: String::from("a"), to: String::from("b") }
+        );
+        assert_eq!(action("alter table t rename a to b"), action("ALTER TABLE t RENAME COLUMN a TO b"));
+        assert_eq!(action("ALTER TABLE t RENAME TO u"), AlterAction::RenameTable(String::from("u")));
+        assert_eq!(action("ALTER TABLE t DROP COLUMN c"), AlterAction::DropColumn(String::from("c")));
+        assert!(parse("ALTER TABLE t MODIFY c").is_err());
+    }
+
+    #[test]
+    fn parses_drop_table_and_drop_index() {
+        assert_eq!(
+            parse("DROP TABLE IF EXISTS t;").unwrap(),
+            Statement::DropTable { name: String::from("t"), if_exists: true }
+        );
+        assert_eq!(
+            parse("drop index i").unwrap(),
+            Statement::DropIndex { name: String::from("i"), if_exists: false }
+        );
+        assert!(parse("DROP VIEW v").is_err());
+    }
+
+    #[test]
+    fn parses_savepoint_statements() {
+        assert_eq!(parse("SAVEPOINT sp").unwrap(), Statement::Savepoint(String::from("sp")));
+        assert_eq!(parse("RELEASE SAVEPOINT sp").unwrap(), Statement::Release(String::from("sp")));
+        assert_eq!(parse("release sp").unwrap(), Statement::Release(String::from("sp")));
+        assert_eq!(parse("ROLLBACK TRANSACTION TO SAVEPOINT sp").unwrap(), Statement::RollbackTo(String::from("sp")));
+        assert_eq!(parse("ROLLBACK TO sp").unwrap(), Statement::RollbackTo(String::from("sp")));
+        assert_eq!(parse("ROLLBACK").unwrap(), Statement::Rollback);
+    }
+
+    #[test]
+    fn reports_line_and_column_of_syntax_errors() {
+        let err = parse("SELECT *\nFROM users\nWHERE id = = 1").unwrap_err();
+        assert_eq!((err.line, err.col), (3, 12));
+
+        let err = parse("SELECT 'unterminated").unwrap_err();
+        assert_eq!((err.line, err.col), (1, 8));
+    }
+
+    #[test]
+    fn rejects_expressions_nested_too_deeply() {
+        for (open, close) in [("(", ")"), ("-(", ")"), ("NOT (", ")")] {
+            let sql = format!("SELECT * FROM users WHERE {}1{}", open.repeat(10_000), close.repeat(10_000));
+            assert!(parse(&sql).unwrap_err().message.contains("nested"), "{open}");
+        }
+        let chain = format!("SELECT * FROM users WHERE id{}", " + 1".repeat(10_000));
+        assert!(parse(&chain).unwrap_err().message.contains("nested"));
+        let predicates = format!("SELECT * FROM users WHERE id{}", " IS NULL".repeat(10_000));
+        assert!(parse(&predicates).is_err());
+        assert!(parse(&format!("SELECT * FROM users WHERE {}1{}", "(".repeat(50), ")".repeat(50))).is_ok());
+    }
+
+    #[test]
+    fn reads_numbers_at_the_edges_of_their_range() {
+        let value = |sql: &str| match parse(&format!("SELECT {sql}")).unwrap() {
+            Statement::Select(select) => select.columns[0].clone(),
+            other => panic!("expected a select, got {other:?}"),
+        };
+        let literal = |literal| ResultColumn::Expr(Expr::Literal(literal));
+        assert_eq!(value("-9223372036854775808"), literal(Literal::Integer(i64::MIN)));
+        assert_eq!(value("9223372036854775807"), literal(Literal::Integer(i64::MAX)));
+        assert_eq!(value("9223372036854775808"), literal(Literal::Real(9223372036854775808.0)));
+        assert_eq!(value("- -9223372036854775808"), literal(Literal::Real(9223372036854775808.0)));
+        assert_eq!(value("1e308"), literal(Literal::Real(1e308)));
+        assert_eq!(value("1e-400"), literal(Literal::Real(0.0)));
+
+        let err = parse("SELECT 1,\n  1e400").unwrap_err();
+        assert_eq!((err.message.as_str(), err.line, err.col), ("number out of range '1e400'", 2, 3));
+        assert!(parse(&format!("SELECT {}", "9".repeat(400))).is_err());
+        assert!(parse("CREATE TABLE t (a VARCHAR(-9223372036854775808))").is_ok());
+        assert!(parse("CREATE TABLE t (a VARCHAR(9223372036854775808))").is_err());
+    }
+
+    #[test]
+    fn numbers_parameters_the_way_sqlite_does() {
+        let sql = "UPDATE users SET name = ? WHERE id = ?3 OR id = :id OR email = ? OR name = :id";
+        let (statement, parameters) = parse_with_parameters(sql).unwrap();
+        let Statement::Update(update) = statement else {
+            panic!("expected an update");
+        };
+        assert_eq!(update.assignments[0].1, Expr::Parameter(1));
+        assert_eq!(update.where_clause.unwrap().to_string(), "(((id = ?3) OR (id = ?4)) OR (email = ?5)) OR (name = ?4)");
+        let names: Vec<Option<&str>> = parameters.iter().map(Option::as_deref).collect();
+        assert_eq!(names, [None, None, Some("?3"), Some(":id"), None]);
+
+        assert!(parse("SELECT ?0").is_err());
+        assert!(parse("SELECT ?32767").is_err());
+        assert!(parse("CREATE TABLE t (a INTEGER DEFAULT ?)").is_err());
+    }
+
+    #[test]
+    fn reads_only_sql_in_any_keyword_case() {
+        assert!(parse("insert 1 alice alice@example.com").is_err());
+        assert!(parse("select").is_err());
+        assert_eq!(parse("select 1"), parse("SELECT 1"));
+        assert_eq!(
+            parse("select 1").unwrap(),
+            Statement::Select(Select {
+                columns: vec![ResultColumn::Expr(Expr::Literal(Literal::Integer(1)))],
+                from: None,
+                where_clause: None,
+            })
+        );
+    }
+}
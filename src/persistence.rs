@@ -1,17 +1,395 @@
-use crate::statement::SelectError;
-use crate::table::{PAGE_SIZE, Page, TABLE_MAX_PAGES};
-use log::error;
+use crate::crypto::PageCipher;
+use crate::frames::FrameMap;
+use crate::header::{DEFAULT_PAGE_SIZE, FLAG_COMPRESSED, FLAG_ENCRYPTED, FileHeader, HEADER_SIZE};
+use crate::table::{Page, TABLE_MAX_PAGES};
+use memmap2::{Mmap, MmapOptions};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io;
-use std::io::{Error, Seek, SeekFrom, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// A free page starts with the number of the next page on the freelist.
+const FREE_PAGE_NEXT_SIZE: usize = size_of::<u32>();
+
+/// Options that control how a database file is opened.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PagerConfig {
+    /// Maximum number of bytes of the file to memory-map for reads.
+    /// `0` disables the mmap read path entirely.
+    pub mmap_size: usize,
+    /// Page size used when the file is created. Existing databases keep the
+    /// page size recorded in their header.
+    pub page_size: usize,
+    /// Compress pages when the file is created. Like the page size, this is
+    /// recorded in the header and ignored for existing databases.
+    pub compress: bool,
+    /// Passphrase for an encrypted database. A new database is encrypted
+    /// when one is given; an existing one must be opened with the same key.
+    pub key: Option<String>,
+}
+
+impl Default for PagerConfig {
+    fn default() -> Self {
+        Self {
+            mmap_size: 0,
+            page_size: DEFAULT_PAGE_SIZE,
+            compress: false,
+            key: None,
+        }
+    }
+}
+
+/// Storage figures about a database file.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PagerStats {
+    /// Bytes per page.
+    pub page_size: usize,
+    /// Pages the file has, the header's page 0 included.
+    pub page_count: usize,
+    /// Pages on the freelist, waiting to be reused.
+    pub free_pages: usize,
+    /// Whether pages are stored compressed.
+    pub compressed: bool,
+    /// Whether pages are stored encrypted.
+    pub encrypted: bool,
+    /// Uncompressed size of the pages written to disk.
+    pub logical_bytes: usize,
+    /// Bytes those pages take up on disk.
+    pub stored_bytes: usize,
+}
+
+/// A named point inside a transaction that `ROLLBACK TO` can return to.
+pub struct Savepoint {
+    pub name: String,
+    header: FileHeader,
+    /// What each page changed since the savepoint looked like when it was
+    /// set: its cached copy, if any, and whether that copy was dirty.
+    /// Pages changed after a newer savepoint are kept by that one instead.
+    journal: BTreeMap<usize, (Option<Box<Page>>, bool)>,
+}
 
 pub struct Pager {
     pub(crate) file: File,
+    /// Number of bytes currently on disk.
     pub(crate) content_length: usize,
+    pub(crate) header: FileHeader,
+    /// The header as last written, which a rollback returns to.
+    pub(crate) committed_header: FileHeader,
     pub(crate) pages: [Option<Box<Page>>; TABLE_MAX_PAGES],
+    /// Cached pages changed since they were last written out.
+    pub(crate) dirty: [bool; TABLE_MAX_PAGES],
+    pub(crate) mmap: Option<Mmap>,
+    /// Where each page lives on disk when pages are stored in frames rather
+    /// than at `page_num * page_size`.
+    pub(crate) frames: Option<FrameMap>,
+    pub(crate) cipher: Option<PageCipher>,
+    /// Open savepoints, innermost last.
+    pub(crate) savepoints: Vec<Savepoint>,
 }
 
 impl Pager {
+    pub fn open(file: File, config: &PagerConfig) -> io::Result<Self> {
+        let mut file = file;
+        // lseek(fd, 0, SEEK_END)
+        let file_length = file.seek(SeekFrom::End(0))? as usize;
+
+        let mut cipher = None;
+        let header = if file_length == 0 {
+            let mut header = FileHeader::new(config.page_size)?;
+            if config.compress {
+                header.flags |= FLAG_COMPRESSED;
+            }
+            if let Some(key) = &config.key {
+                let (page_cipher, salt, key_check) = PageCipher::create(key);
+                header.flags |= FLAG_ENCRYPTED;
+                header.salt = salt;
+                header.key_check = key_check;
+                cipher = Some(page_cipher);
+            }
+            header
+        } else {
+            let mut bytes = [0u8; HEADER_SIZE];
+            file.seek(SeekFrom::Start(0))?;
+            file.read_exact(&mut bytes).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, "file is not a rustqlite database")
+            })?;
+            let header = FileHeader::read_from(&bytes)?;
+            match (&config.key, header.is_encrypted()) {
+                (Some(key), true) => {
+                    cipher = Some(PageCipher::open(key, &header.salt, &header.key_check)?);
+                }
+                (None, true) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "database is encrypted, a key is required",
+                    ));
+                }
+                (Some(_), false) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "database is not encrypted",
+                    ));
+                }
+                (None, false) => {}
+            }
+            header
+        };
+
+        let frames = if header.is_framed() {
+            Some(FrameMap::scan(&mut file, header.page_size as u64, file_length as u64)?)
+        } else {
+            None
+        };
+
+        // Framed pages have to be decoded, so they can't be served from a mapping.
+        let map_len = if frames.is_some() { 0 } else { file_length.min(config.mmap_size) };
+        let mmap = if map_len > 0 {
+            // SAFETY: the mapping is read-only and every write to the file goes
+            // through this pager. Views handed out by `mapped_page` borrow the
+            // pager, so no write can happen while one is alive.
+            Some(unsafe { MmapOptions::new().len(map_len).map(&file)? })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            file,
+            content_length: file_length,
+            committed_header: header.clone(),
+            header,
+            pages: std::array::from_fn(|_| None),
+            dirty: [false; TABLE_MAX_PAGES],
+            mmap,
+            frames,
+            cipher,
+            savepoints: Vec::new(),
+        })
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.header.page_size
+    }
+
+    /// Returns a read-only view of `page_num` straight from the mapped file,
+    /// or `None` if the page lies (partly) beyond the mapping.
+    pub fn mapped_page(&self, page_num: usize) -> Option<&[u8]> {
+        let mmap = self.mmap.as_ref()?;
+        let start = page_num * self.page_size();
+        let end = start + self.page_size();
+        if end > mmap.len() {
+            return None;
+        }
+        Some(&mmap[start..end])
+    }
+
+    /// Fills `page` with the on-disk contents of `page_num`. Pages that were
+    /// never written are left zeroed.
+    pub fn read_page_into(&mut self, page_num: usize, page: &mut [u8]) -> io::Result<()> {
+        if let Some(frames) = &self.frames {
+            if let Some(data) = frames.read(&mut self.file, page_num)? {
+                self.decode_page(page_num, data, page)?;
+            }
+            return Ok(());
+        }
+
+        let page_size = self.page_size();
+        let mut num_pages = self.content_length / page_size;
+        if !self.content_length.is_multiple_of(page_size) {
+            num_pages += 1;
+        }
+
+        if page_num < num_pages {
+            let offset = (page_num * page_size) as u64;
+            self.file.seek(SeekFrom::Start(offset))?;
+
+            let bytes_to_read =
+                if page_num == num_pages - 1 && !self.content_length.is_multiple_of(page_size) {
+                    self.content_length % page_size
+                } else {
+                    page_size
+                };
+
+            self.file.read_exact(&mut page[..bytes_to_read])?;
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> PagerStats {
+        let page_size = self.page_size();
+        let (logical_bytes, stored_bytes) = match &self.frames {
+            Some(frames) => (frames.page_frames() * page_size, frames.stored_bytes()),
+            None => {
+                let data = self.content_length.saturating_sub(page_size);
+                (data, data)
+            }
+        };
+        PagerStats {
+            page_size,
+            page_count: self.header.page_count,
+            free_pages: self.header.free_page_count,
+            compressed: self.header.is_compressed(),
+            encrypted: self.header.is_encrypted(),
+            logical_bytes,
+            stored_bytes,
+        }
+    }
+
+    /// Hands out a page as a zeroed, cached page: the first one on the
+    /// freelist, or else the next unused page number. Returns `None` once
+    /// the file has reached `TABLE_MAX_PAGES`, or if the freelist can't be
+    /// read.
+    pub fn allocate_page(&mut self) -> Option<usize> {
+        let page_size = self.page_size();
+        let page_num = match self.header.first_free_page {
+            0 => {
+                let page_num = self.header.page_count;
+                if page_num >= TABLE_MAX_PAGES {
+                    return None;
+                }
+                self.header.page_count += 1;
+                page_num
+            }
+            page_num if page_num < TABLE_MAX_PAGES => {
+                let next = match &self.pages[page_num] {
+                    Some(page) => read_u32(page),
+                    None => {
+                        let mut page = vec![0u8; page_size];
+                        self.read_page_into(page_num, &mut page).ok()?;
+                        read_u32(&page)
+                    }
+                };
+                self.header.first_free_page = next as usize;
+                self.header.free_page_count = self.header.free_page_count.saturating_sub(1);
+                page_num
+            }
+            _ => return None,
+        };
+        self.journal_page(page_num);
+        self.pages[page_num] = Some(vec![0u8; page_size].into_boxed_slice());
+        self.dirty[page_num] = true;
+        Some(page_num)
+    }
+
+    /// Puts `page_num` at the head of the freelist. Whatever the page held is
+    /// dropped; it only keeps the number of the next free page.
+    pub fn free_page(&mut self, page_num: usize) {
+        if page_num >= TABLE_MAX_PAGES {
+            return;
+        }
+        self.journal_page(page_num);
+        let mut page = vec![0u8; self.page_size()];
+        page[..FREE_PAGE_NEXT_SIZE].copy_from_slice(&(self.header.first_free_page as u32).to_le_bytes());
+        self.pages[page_num] = Some(page.into_boxed_slice());
+        self.dirty[page_num] = true;
+        self.header.first_free_page = page_num;
+        self.header.free_page_count += 1;
+    }
+
+    /// Writes the header into page 0, padding the page out to the page size.
+    pub fn write_header(&mut self) -> io::Result<()> {
+        let mut page = vec![0u8; self.page_size()];
+        self.header.write_to(&mut page);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&page)?;
+        self.file.flush()?;
+        self.content_length = self.content_length.max(page.len());
+        self.committed_header = self.header.clone();
+        Ok(())
+    }
+
+    /// Writes every page changed since the last commit, then the header, and
+    /// waits for both to reach the disk. Open savepoints end with it.
+    ///
+    /// Pages are overwritten in place and there is no journal to restore
+    /// them from, so a commit cut short by a crash can leave some pages
+    /// new, some old and the header from the commit before.
+    pub fn commit(&mut self) -> io::Result<()> {
+        self.savepoints.clear();
+        let page_size = self.page_size();
+        for page_num in 0..TABLE_MAX_PAGES {
+            if self.dirty[page_num] {
+                self.flush(page_num, page_size)?;
+                self.dirty[page_num] = false;
+            }
+        }
+        self.write_header()?;
+        self.file.sync_data()
+    }
+
+    /// Throws away every change since the last commit. Changed pages leave
+    /// the cache, to be read back from disk when next needed, and the header
+    /// returns to the one on disk, which also forgets pages allocated since.
+    pub fn rollback(&mut self) {
+        self.savepoints.clear();
+        for page_num in 0..TABLE_MAX_PAGES {
+            if self.dirty[page_num] {
+                self.pages[page_num] = None;
+                self.dirty[page_num] = false;
+            }
+        }
+        self.header = self.committed_header.clone();
+    }
+
+    /// Sets a savepoint called `name`, inside any that are already open.
+    pub fn begin_savepoint(&mut self, name: &str) {
+        self.savepoints.push(Savepoint {
+            name: name.to_string(),
+            header: self.header.clone(),
+            journal: BTreeMap::new(),
+        });
+    }
+
+    /// The position of the innermost open savepoint called `name`.
+    pub fn find_savepoint(&self, name: &str) -> Option<usize> {
+        self.savepoints.iter().rposition(|savepoint| savepoint.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Ends the `index`-th savepoint and the ones inside it, keeping their
+    /// changes. The savepoint around them takes over what they journaled.
+    pub fn release_savepoint(&mut self, index: usize) {
+        let released = self.savepoints.split_off(index);
+        if let Some(outer) = self.savepoints.last_mut() {
+            for (page_num, saved) in released.into_iter().flat_map(|savepoint| savepoint.journal) {
+                outer.journal.entry(page_num).or_insert(saved);
+            }
+        }
+    }
+
+    /// Undoes every change since the `index`-th savepoint was set, which
+    /// stays open; the ones inside it end. Only pages changed since then
+    /// are touched.
+    pub fn rollback_to_savepoint(&mut self, index: usize) {
+        // Innermost first, so the oldest copy of a page is the one left.
+        for savepoint in self.savepoints.drain(index + 1..).rev().collect::<Vec<_>>() {
+            self.restore(savepoint.journal);
+        }
+        let savepoint = &mut self.savepoints[index];
+        let journal = std::mem::take(&mut savepoint.journal);
+        self.header = savepoint.header.clone();
+        self.restore(journal);
+    }
+
+    fn restore(&mut self, journal: BTreeMap<usize, (Option<Box<Page>>, bool)>) {
+        for (page_num, (page, dirty)) in journal {
+            self.pages[page_num] = page;
+            self.dirty[page_num] = dirty;
+        }
+    }
+
+    /// Keeps a copy of `page_num` for the innermost savepoint, unless it has
+    /// one already. Called before any change to the page.
+    pub fn journal_page(&mut self, page_num: usize) {
+        let Some(savepoint) = self.savepoints.last_mut() else {
+            return;
+        };
+        savepoint
+            .journal
+            .entry(page_num)
+            .or_insert_with(|| (self.pages[page_num].clone(), self.dirty[page_num]));
+    }
+
     pub fn flush(&mut self, page_num: usize, size: usize) -> io::Result<()> {
         if page_num >= TABLE_MAX_PAGES {
             return Err(io::Error::new(
@@ -20,21 +398,69 @@ impl Pager {
             ));
         }
 
+        let page_size = self.page_size();
         let page = self.pages[page_num]
             .as_ref()
             .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "tried to flush null page"))?;
 
-        if size > PAGE_SIZE {
+        if size > page_size {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
-                "size > PAGE_SIZE",
+                "size > page size",
             ));
         }
 
-        let offset = (page_num * PAGE_SIZE) as u64;
+        let framed = self.frames.is_some().then(|| self.encode_page(page_num, &page[..size]));
+        if let (Some(data), Some(frames)) = (framed, &mut self.frames) {
+            frames.write(&mut self.file, page_num, &data)?;
+            self.file.flush()?;
+            return Ok(());
+        }
+
+        let offset = (page_num * page_size) as u64;
         self.file.seek(SeekFrom::Start(offset))?;
         self.file.write_all(&page[..size])?;
-        self.file.flush()?; // optional; for durability use sync_data/sync_all
+        self.file.flush()?;
+        self.content_length = self.content_length.max(offset as usize + size);
         Ok(())
     }
+
+    /// Turns a page into the bytes stored in its frame: compressed first,
+    /// then encrypted, depending on the database's flags.
+    fn encode_page(&self, page_num: usize, page: &[u8]) -> Vec<u8> {
+        let data = if self.header.is_compressed() {
+            lz4_flex::block::compress(page)
+        } else {
+            page.to_vec()
+        };
+        match &self.cipher {
+            Some(cipher) => cipher.encrypt_page(page_num, &data),
+            None => data,
+        }
+    }
+
+    fn decode_page(&self, page_num: usize, data: Vec<u8>, page: &mut [u8]) -> io::Result<()> {
+        let data = match &self.cipher {
+            Some(cipher) => cipher.decrypt_page(page_num, &data)?,
+            None => data,
+        };
+        if !self.header.is_compressed() {
+            if data.len() != page.len() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "stored page has the wrong size"));
+            }
+            page.copy_from_slice(&data);
+            return Ok(());
+        }
+
+        let len = lz4_flex::block::decompress_into(&data, page)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if len != page.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "page decompressed to the wrong size"));
+        }
+        Ok(())
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..FREE_PAGE_NEXT_SIZE].try_into().unwrap())
 }
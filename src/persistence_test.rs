@@ -0,0 +1,286 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::cursor::Cursor;
+    use crate::persistence::PagerConfig;
+    use crate::ast::Statement;
+    use crate::database::Database;
+    use crate::error::{Error, Object};
+    use crate::parser::parse;
+    use crate::table::{fetch_row, insert_row};
+    use crate::value::Value;
+
+    fn user(id: i64, name: &str, email: &str) -> Vec<Value> {
+        vec![
+            Value::Integer(id),
+            Value::Text(name.to_string()),
+            Value::Text(email.to_string()),
+        ]
+    }
+
+    fn text(s: impl Into<String>) -> Value {
+        Value::Text(s.into())
+    }
+
+    #[test]
+    fn mmap_serves_full_pages_and_falls_back_past_the_mapping() {
+        let test_database_name = String::from("test_db_mmap");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        insert_row(&mut cur, 1, &user(1, "alice", "alice@example.com")).unwrap();
+        insert_row(&mut cur, 20, &user(20, "bob", "bob@example.com")).unwrap();
+        cur.table.pager.commit().unwrap();
+
+        let config = PagerConfig {
+            mmap_size: 3 * 4096,
+            ..PagerConfig::default()
+        };
+        let mut db = Database::open_with(&test_database_name, &config).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        assert!(table.pager.mapped_page(2).is_some());
+        assert!(table.pager.mapped_page(3).is_none());
+
+        let mut cur = Cursor::new(&mut table);
+        let alice = fetch_row(&mut cur, 1).unwrap();
+        let bob = fetch_row(&mut cur, 20).unwrap();
+        assert_eq!(alice.values[1], text("alice"));
+        assert_eq!(bob.values[2], text("bob@example.com"));
+        // The table's root is mapped; its leaf lies past the mapping.
+        assert_eq!(cur.table.leaves, [4]);
+        assert!(cur.table.pager.pages[2].is_none());
+        assert!(cur.table.pager.pages[4].is_some());
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn page_size_is_chosen_at_creation_and_read_back_from_the_header() {
+        let test_database_name = String::from("test_db_page_size");
+        let config = PagerConfig {
+            page_size: 512,
+            ..PagerConfig::default()
+        };
+        let mut db = Database::open_with(&test_database_name, &config).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        insert_row(&mut cur, 3, &user(3, "carol", "carol@example.com")).unwrap();
+        cur.table.pager.commit().unwrap();
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        assert_eq!(table.pager.page_size(), 512);
+        assert_eq!(table.max_rowid, 3);
+        let mut cur = Cursor::new(&mut table);
+        let carol = fetch_row(&mut cur, 3).unwrap();
+        assert_eq!(carol.values[1], text("carol"));
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn long_values_spill_into_overflow_pages_and_are_reassembled() {
+        let test_database_name = String::from("test_db_overflow");
+        let long_email = "x".repeat(10_000);
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        insert_row(&mut cur, 0, &user(0, "dave", &long_email)).unwrap();
+        insert_row(&mut cur, 1, &user(1, "erin", "erin@example.com")).unwrap();
+        cur.table.pager.commit().unwrap();
+        let pages_after_first_write = cur.table.pager.header.page_count;
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        assert_eq!(fetch_row(&mut cur, 0).unwrap().values[2], text(long_email));
+        assert_eq!(fetch_row(&mut cur, 1).unwrap().values[2], text("erin@example.com"));
+
+        // Rewriting the value reuses the existing chain instead of growing the file.
+        insert_row(&mut cur, 0, &user(0, "dave", &"y".repeat(9_000))).unwrap();
+        assert_eq!(cur.table.pager.header.page_count, pages_after_first_write);
+        assert_eq!(fetch_row(&mut cur, 0).unwrap().values[2], text("y".repeat(9_000)));
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn compressed_pages_round_trip_and_take_less_space() {
+        let test_database_name = String::from("test_db_compressed");
+        let config = PagerConfig {
+            compress: true,
+            ..PagerConfig::default()
+        };
+        let mut db = Database::open_with(&test_database_name, &config).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        for id in 0..30 {
+            insert_row(&mut cur, id, &user(id, "frank", &"frank@example.com".repeat(20))).unwrap();
+        }
+        cur.table.pager.commit().unwrap();
+
+        // The compression flag comes from the header, not from the config.
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let stats = table.pager.stats();
+        assert!(stats.compressed);
+        assert!(stats.stored_bytes * 4 < stats.logical_bytes);
+        let mut cur = Cursor::new(&mut table);
+        assert_eq!(fetch_row(&mut cur, 29).unwrap().values[2], text("frank@example.com".repeat(20)));
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn encrypted_database_needs_the_right_key() {
+        let test_database_name = String::from("test_db_encrypted");
+        let config = PagerConfig {
+            key: Some(String::from("correct horse")),
+            ..PagerConfig::default()
+        };
+        let mut db = Database::open_with(&test_database_name, &config).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        insert_row(&mut cur, 7, &user(7, "grace", "grace@example.com")).unwrap();
+        cur.table.pager.commit().unwrap();
+
+        let on_disk = fs::read(&test_database_name).unwrap();
+        assert!(!on_disk.windows(5).any(|w| w == b"grace"));
+
+        let wrong = PagerConfig {
+            key: Some(String::from("battery staple")),
+            ..PagerConfig::default()
+        };
+        assert!(Database::open_with(&test_database_name, &wrong).is_err());
+        assert!(Database::open(&test_database_name).is_err());
+
+        let mut db = Database::open_with(&test_database_name, &config).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        assert_eq!(fetch_row(&mut cur, 7).unwrap().values[2], text("grace@example.com"));
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn invalid_page_size_is_rejected() {
+        let config = PagerConfig {
+            page_size: 1000,
+            ..PagerConfig::default()
+        };
+        assert!(Database::open_with("test_db_bad_page_size", &config).is_err());
+        fs::remove_file("test_db_bad_page_size").unwrap();
+    }
+
+    #[test]
+    fn rollback_discards_what_the_transaction_changed() {
+        let test_database_name = String::from("test_db_transaction");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        assert!(matches!(db.commit_transaction(), Err(Error::Misuse(_))));
+        assert!(matches!(db.rollback(), Err(Error::Misuse(_))));
+
+        db.begin().unwrap();
+        assert!(matches!(db.begin(), Err(Error::Misuse(_))));
+        let mut table = db.table("users").unwrap();
+        insert_row(&mut Cursor::new(&mut table), 1, &user(1, "alice", "alice@example.com")).unwrap();
+        let Ok(Statement::CreateTable(create)) = parse("CREATE TABLE notes (body TEXT)") else {
+            panic!("expected a create table");
+        };
+        db.create_table(&create).unwrap();
+        let page_count = db.pager.stats().page_count;
+        db.rollback().unwrap();
+
+        assert!(!db.in_transaction());
+        assert!(db.table("notes").is_err());
+
+        // Outside of a transaction a failed statement's changes are discarded the same way.
+        let mut table = db.table("users").unwrap();
+        insert_row(&mut Cursor::new(&mut table), 9, &user(9, "mallory", "m@example.com")).unwrap();
+        db.discard_changes().unwrap();
+        assert!(db.pager.stats().page_count < page_count);
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        assert!(fetch_row(&mut cur, 1).is_err());
+        assert!(fetch_row(&mut cur, 9).is_err());
+
+        db.begin().unwrap();
+        let mut table = db.table("users").unwrap();
+        insert_row(&mut Cursor::new(&mut table), 2, &user(2, "bob", "bob@example.com")).unwrap();
+        db.commit_transaction().unwrap();
+        db.begin().unwrap();
+        let mut table = db.table("users").unwrap();
+        insert_row(&mut Cursor::new(&mut table), 3, &user(3, "carol", "carol@example.com")).unwrap();
+        // Closing without a commit loses the open transaction.
+        db.db_close().unwrap();
+        assert!(!db.in_transaction());
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        assert_eq!(fetch_row(&mut cur, 2).unwrap().values[1], text("bob"));
+        assert!(fetch_row(&mut cur, 3).is_err());
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn rollback_to_a_savepoint_keeps_the_rest_of_the_transaction() {
+        let test_database_name = String::from("test_db_savepoint");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let insert = |db: &mut Database, id: i64, name: &str| {
+            let mut table = db.table("users").unwrap();
+            insert_row(&mut Cursor::new(&mut table), id, &user(id, name, &"@".repeat(3000))).unwrap();
+        };
+
+        db.begin().unwrap();
+        insert(&mut db, 1, "alice");
+        db.savepoint("a");
+        insert(&mut db, 2, "bob");
+        db.savepoint("b");
+        insert(&mut db, 3, "carol");
+        let page_count = db.pager.stats().page_count;
+        db.rollback_to("a").unwrap();
+        assert!(db.pager.stats().page_count < page_count);
+        assert!(matches!(db.release("b"), Err(Error::NotFound(Object::Savepoint, _))));
+
+        // `a` is still open after rolling back to it.
+        insert(&mut db, 4, "dave");
+        db.rollback_to("A").unwrap();
+        insert(&mut db, 5, "erin");
+        db.release("a").unwrap();
+        assert!(db.in_transaction());
+        db.commit_transaction().unwrap();
+
+        // A savepoint outside of a transaction starts one that its release commits.
+        db.savepoint("import");
+        insert(&mut db, 6, "frank");
+        db.release("import").unwrap();
+        assert!(!db.in_transaction());
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        for (id, present) in [(1, true), (2, false), (3, false), (4, false), (5, true), (6, true)] {
+            assert_eq!(fetch_row(&mut cur, id).is_ok(), present, "row {id}");
+        }
+        assert_eq!(fetch_row(&mut cur, 5).unwrap().values[2], text("@".repeat(3000)));
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use crate::error::{Error, Result};
+use crate::value::Value;
+
+/// Serial types, the per-column type codes in a record header. Integers
+/// take the fewest bytes that hold them; `0` and `1` take none at all.
+/// Text and blobs store their length in the code: `12 + 2n` is a blob of
+/// `n` bytes and `13 + 2n` text of `n` bytes.
+const SERIAL_NULL: u64 = 0;
+const SERIAL_REAL: u64 = 7;
+const SERIAL_ZERO: u64 = 8;
+const SERIAL_ONE: u64 = 9;
+const SERIAL_BLOB: u64 = 12;
+const SERIAL_TEXT: u64 = 13;
+
+/// Byte widths of the integer serial types `1` to `6`.
+const INTEGER_WIDTHS: [usize; 6] = [1, 2, 3, 4, 6, 8];
+
+/// Encodes a row as a record: the header's length, one serial type per
+/// value, then the values' bodies, all back to back.
+///
+/// ```text
+/// [header len][type 1]...[type n][body 1]...[body n]
+/// ```
+pub fn encode(values: &[Value]) -> Vec<u8> {
+    let mut types = Vec::new();
+    let mut body = Vec::new();
+    for value in values {
+        let serial_type = match value {
+            Value::Null => SERIAL_NULL,
+            Value::Integer(0) => SERIAL_ZERO,
+            Value::Integer(1) => SERIAL_ONE,
+            Value::Integer(n) => {
+                let width = integer_width(*n);
+                body.extend_from_slice(&n.to_be_bytes()[8 - width..]);
+                INTEGER_WIDTHS.iter().position(|&w| w == width).unwrap() as u64 + 1
+            }
+            Value::Real(n) => {
+                body.extend_from_slice(&n.to_be_bytes());
+                SERIAL_REAL
+            }
+            Value::Text(text) => {
+                body.extend_from_slice(text.as_bytes());
+                SERIAL_TEXT + 2 * text.len() as u64
+            }
+            Value::Blob(bytes) => {
+                body.extend_from_slice(bytes);
+                SERIAL_BLOB + 2 * bytes.len() as u64
+            }
+        };
+        write_varint(&mut types, serial_type);
+    }
+
+    // The length counts itself, which may take an extra byte to store.
+    let mut header_len = types.len() as u64 + 1;
+    if varint_len(header_len) > 1 {
+        header_len += varint_len(header_len + 1) as u64 - 1;
+    }
+    let mut record = Vec::with_capacity(header_len as usize + body.len());
+    write_varint(&mut record, header_len);
+    record.extend(types);
+    record.extend(body);
+    record
+}
+
+/// Decodes a record written by `encode`.
+pub fn decode(record: &[u8]) -> Result<Vec<Value>> {
+    let corrupt = || Error::corrupt("malformed record");
+
+    let (header_len, mut pos) = read_varint(record).ok_or_else(corrupt)?;
+    let header_len = usize::try_from(header_len).map_err(|_| corrupt())?;
+    if header_len > record.len() {
+        return Err(corrupt());
+    }
+
+    let mut body = header_len;
+    let mut values = Vec::new();
+    while pos < header_len {
+        let (serial_type, len) = read_varint(&record[pos..header_len]).ok_or_else(corrupt)?;
+        pos += len;
+
+        let size = body_size(serial_type).ok_or_else(corrupt)?;
+        let bytes = record.get(body..body.saturating_add(size)).ok_or_else(corrupt)?;
+        body += size;
+        values.push(match serial_type {
+            SERIAL_NULL => Value::Null,
+            1..=6 => {
+                // Sign-extend from the stored width.
+                let mut buf = [if bytes[0] & 0x80 != 0 { 0xff } else { 0 }; 8];
+                buf[8 - size..].copy_from_slice(bytes);
+                Value::Integer(i64::from_be_bytes(buf))
+            }
+            SERIAL_REAL => Value::Real(f64::from_be_bytes(bytes.try_into().unwrap())),
+            SERIAL_ZERO => Value::Integer(0),
+            SERIAL_ONE => Value::Integer(1),
+            n if n.is_multiple_of(2) => Value::Blob(bytes.to_vec()),
+            _ => Value::Text(String::from_utf8(bytes.to_vec()).map_err(|_| corrupt())?),
+        });
+    }
+    Ok(values)
+}
+
+/// Size of the body of a value with the given serial type, `None` for the
+/// reserved codes 10 and 11.
+fn body_size(serial_type: u64) -> Option<usize> {
+    match serial_type {
+        SERIAL_NULL | SERIAL_ZERO | SERIAL_ONE => Some(0),
+        1..=6 => Some(INTEGER_WIDTHS[serial_type as usize - 1]),
+        SERIAL_REAL => Some(8),
+        10 | 11 => None,
+        n => usize::try_from((n - SERIAL_BLOB) / 2).ok(),
+    }
+}
+
+/// The fewest bytes, among the widths serial types offer, that hold `n`.
+fn integer_width(n: i64) -> usize {
+    INTEGER_WIDTHS
+        .into_iter()
+        .find(|&width| {
+            let bits = 8 * width as u32;
+            bits == 64 || (-(1i64 << (bits - 1))..1i64 << (bits - 1)).contains(&n)
+        })
+        .unwrap()
+}
+
+/// Appends `n` as a little-endian base-128 varint: seven bits per byte, the
+/// high bit set on every byte but the last.
+pub fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Reads a varint from the start of `bytes`, returning it and the number of
+/// bytes it took. `None` if `bytes` ends first.
+pub fn read_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut n = 0u64;
+    for (i, &byte) in bytes.iter().enumerate().take(10) {
+        n |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((n, i + 1));
+        }
+    }
+    None
+}
+
+pub fn varint_len(n: u64) -> usize {
+    (64 - n.leading_zeros() as usize).max(1).div_ceil(7)
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::cursor::Cursor;
+    use crate::database::Database;
+    use crate::record::{decode, encode, read_varint, write_varint};
+    use crate::table::{cursor_row, delete_row, fetch_row, insert_row};
+    use crate::value::Value;
+
+    #[test]
+    fn varints_round_trip_at_every_width() {
+        for n in [0, 1, 127, 128, 300, 1 << 35, u64::MAX] {
+            let mut bytes = vec![];
+            write_varint(&mut bytes, n);
+            assert_eq!(read_varint(&bytes), Some((n, bytes.len())));
+        }
+        assert_eq!(read_varint(&[0x80, 0x80]), None);
+    }
+
+    #[test]
+    fn records_store_each_value_at_its_own_width() {
+        let values = vec![
+            Value::Null,
+            Value::Integer(0),
+            Value::Integer(1),
+            Value::Integer(-100),
+            Value::Integer(40_000),
+            Value::Integer(i64::MIN),
+            Value::Real(2.5),
+            Value::Text(String::from("héllo")),
+            Value::Blob(vec![0, 255, 7]),
+            Value::Text(String::new()),
+        ];
+        let record = encode(&values);
+        assert_eq!(decode(&record).unwrap(), values);
+
+        // A small integer and a short string take a byte or two, not a fixed slot.
+        let small = encode(&[Value::Integer(5), Value::Text(String::from("ab"))]);
+        assert_eq!(small, [3, 1, 17, 5, b'a', b'b']);
+
+        assert!(decode(&small[..4]).is_err());
+        assert!(decode(&[2, 10]).is_err());
+    }
+
+    #[test]
+    fn leaves_split_when_full_and_keep_rows_in_key_order() {
+        let test_database_name = String::from("test_db_slotted");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        // Every other key, then the ones in between, so rows land mid-leaf.
+        for id in (0..200).step_by(2).chain((1..200).step_by(2)) {
+            let email = "e".repeat(id as usize % 50);
+            let values = [Value::Integer(id), Value::Text(format!("user{id}")), Value::Text(email)];
+            insert_row(&mut cur, id, &values).unwrap();
+        }
+        for id in 50..150 {
+            delete_row(&mut cur, id).unwrap();
+        }
+        assert!(cur.table.leaves.len() > 1);
+        cur.table.pager.commit().unwrap();
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        let mut ids = vec![];
+        while !cur.end_of_table() {
+            if let Some(row) = cursor_row(&mut cur).unwrap() {
+                assert_eq!(row.values[1], Value::Text(format!("user{}", row.key)));
+                ids.push(row.key);
+            }
+            cur.advance();
+        }
+        assert_eq!(ids, (0..50).chain(150..200).collect::<Vec<_>>());
+        for id in (0..50).chain(150..200) {
+            assert_eq!(fetch_row(&mut cur, id).unwrap().key, id);
+        }
+        for id in [-1, 50, 100, 149, 200] {
+            assert!(fetch_row(&mut cur, id).is_err());
+        }
+        assert_eq!(fetch_row(&mut cur, 199).unwrap().values[2], Value::Text("e".repeat(49)));
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+}
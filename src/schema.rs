@@ -0,0 +1,191 @@
+use crate::ast::{ColumnConstraint, ColumnDef, CreateTable, Expr, Statement};
+use crate::error::{Error, Object, Result};
+use crate::eval;
+use crate::parser;
+use crate::value::Value;
+
+/// The table the tests work on, the one the REPL gives a new database.
+#[cfg(test)]
+pub const USERS_SCHEMA: &str =
+    "CREATE TABLE IF NOT EXISTS users (id INTEGER PRIMARY KEY, name VARCHAR(32), email VARCHAR(255))";
+
+/// The catalog of tables and indexes, rooted at `SCHEMA_ROOT_PAGE`.
+pub const CATALOG_TABLE_NAME: &str = "rustqlite_schema";
+
+pub const CATALOG_SCHEMA: &str = "CREATE TABLE rustqlite_schema \
+    (type VARCHAR(8), name VARCHAR(32), tbl_name VARCHAR(32), rootpage INTEGER, sql VARCHAR(200))";
+
+/// A column's type affinity: the kind of value it prefers to store. Values
+/// are converted to it on the way in where that loses nothing, and stored
+/// as they are otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnType {
+    Integer,
+    Real,
+    Text,
+    Numeric,
+    /// No preference; values are stored exactly as given.
+    Blob,
+}
+
+impl ColumnType {
+    /// Derives the affinity from a declared type name with SQLite's rules,
+    /// checked in order.
+    fn from_type_name(type_name: Option<&str>) -> Self {
+        let type_name = type_name.unwrap_or_default().to_ascii_uppercase();
+        let contains = |names: &[&str]| names.iter().any(|name| type_name.contains(name));
+        if contains(&["INT"]) {
+            ColumnType::Integer
+        } else if contains(&["CHAR", "CLOB", "TEXT"]) {
+            ColumnType::Text
+        } else if type_name.is_empty() || contains(&["BLOB"]) {
+            ColumnType::Blob
+        } else if contains(&["REAL", "FLOA", "DOUB"]) {
+            ColumnType::Real
+        } else {
+            ColumnType::Numeric
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub ty: ColumnType,
+    pub not_null: bool,
+    /// Set for `UNIQUE` and `PRIMARY KEY` columns.
+    pub unique: bool,
+    /// The value of the column when an insert leaves it out.
+    pub default: Option<Expr>,
+    /// `CHECK` expressions every stored row must not make false.
+    pub checks: Vec<Expr>,
+}
+
+impl Column {
+    /// The value the column takes when a row doesn't give one.
+    pub fn default_value(&self) -> Value {
+        // Defaults are checked to be constant when the schema is built.
+        let default = self.default.as_ref().and_then(|expr| eval::evaluate(expr, &|_| None).ok());
+        default.unwrap_or(Value::Null).with_affinity(self.ty)
+    }
+}
+
+/// A table definition, as parsed from its `CREATE TABLE` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    pub name: String,
+    pub columns: Vec<Column>,
+    /// The `INTEGER PRIMARY KEY` column, an alias for the rowid every row is
+    /// stored under. Tables without one keep the rowid out of sight.
+    pub key: Option<usize>,
+    /// Whether the key is `AUTOINCREMENT`, so rowids are never reused.
+    pub autoincrement: bool,
+    pub create: CreateTable,
+}
+
+impl Schema {
+    pub fn new(create: &CreateTable) -> Result<Self> {
+        let mut columns: Vec<Column> = Vec::with_capacity(create.columns.len());
+        for def in &create.columns {
+            if columns.iter().any(|c| c.name.eq_ignore_ascii_case(&def.name)) {
+                return Err(Error::AlreadyExists(Object::Column, def.name.clone()));
+            }
+            let mut column = Column {
+                name: def.name.clone(),
+                ty: ColumnType::from_type_name(def.type_name.as_deref()),
+                not_null: false,
+                unique: false,
+                default: None,
+                checks: Vec::new(),
+            };
+            for constraint in &def.constraints {
+                match constraint {
+                    ColumnConstraint::PrimaryKey { .. } | ColumnConstraint::Unique => column.unique = true,
+                    ColumnConstraint::NotNull => column.not_null = true,
+                    ColumnConstraint::Default(expr) => {
+                        if eval::evaluate(expr, &|_| None).is_err() {
+                            return Err(Error::misuse(format!("Default value of column {} is not constant.", def.name)));
+                        }
+                        column.default = Some(expr.clone());
+                    }
+                    ColumnConstraint::Check(expr) => column.checks.push(expr.clone()),
+                }
+            }
+            columns.push(column);
+        }
+
+        // Checks may refer to any column of the table.
+        let known = |name: &str| create.columns.iter().any(|def| def.name.eq_ignore_ascii_case(name));
+        for check in columns.iter().flat_map(|column| &column.checks) {
+            if let Err(err @ Error::NotFound(Object::Column, _)) =
+                eval::evaluate(check, &|name| known(name).then_some(Value::Null))
+            {
+                return Err(err);
+            }
+        }
+
+        let key = create.columns.iter().zip(&columns).position(|(def, column)| {
+            column.ty == ColumnType::Integer
+                && def.constraints.iter().any(|c| matches!(c, ColumnConstraint::PrimaryKey { .. }))
+        });
+        let autoincrement = |def: &ColumnDef| {
+            def.constraints
+                .iter()
+                .any(|c| matches!(c, ColumnConstraint::PrimaryKey { autoincrement: true }))
+        };
+        let misplaced = create
+            .columns
+            .iter()
+            .enumerate()
+            .find(|&(index, def)| key != Some(index) && autoincrement(def));
+        if let Some((_, def)) = misplaced {
+            return Err(Error::misuse(format!(
+                "AUTOINCREMENT is only allowed on an INTEGER PRIMARY KEY, not {}.",
+                def.name
+            )));
+        }
+
+        Ok(Self {
+            name: create.name.clone(),
+            autoincrement: key.is_some_and(|key| autoincrement(&create.columns[key])),
+            columns,
+            key,
+            create: create.clone(),
+        })
+    }
+
+    /// Rebuilds a schema from the SQL stored in the database.
+    pub fn from_sql(sql: &str) -> Result<Self> {
+        match parser::parse(sql) {
+            Ok(Statement::CreateTable(create)) => Self::new(&create),
+            Ok(_) => Err(Error::corrupt("stored schema is not a CREATE TABLE statement")),
+            Err(err) => Err(Error::corrupt(format!("stored schema does not parse: {err}"))),
+        }
+    }
+
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn key_column(&self) -> Option<&Column> {
+        self.key.map(|key| &self.columns[key])
+    }
+
+    /// Whether `name` refers to the rowid: the key column, or one of the
+    /// names `rowid`, `oid` and `_rowid_` unless a column has taken it.
+    pub fn is_rowid(&self, name: &str) -> bool {
+        match self.column_index(name) {
+            Some(index) => self.key == Some(index),
+            None => ["rowid", "oid", "_rowid_"].iter().any(|alias| alias.eq_ignore_ascii_case(name)),
+        }
+    }
+
+    /// The SQL the schema is stored as.
+    pub fn sql(&self) -> String {
+        CreateTable {
+            if_not_exists: false,
+            ..self.create.clone()
+        }
+        .to_string()
+    }
+}
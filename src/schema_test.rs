@@ -0,0 +1,303 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::ast::{ColumnConstraint, CreateTable, Expr, Insert, Literal, Statement};
+    use crate::cursor::Cursor;
+    use crate::parser::parse;
+    use crate::error::{Constraint, Error, Object, Result};
+    use crate::header::SCHEMA_ROOT_PAGE;
+    use crate::schema::{CATALOG_SCHEMA, CATALOG_TABLE_NAME, ColumnType, Schema};
+    use crate::statement::insert;
+    use crate::database::Database;
+    use crate::table::{fetch_row, insert_row, Table};
+    use crate::value::Value;
+
+    fn parse_create(sql: &str) -> CreateTable {
+        match parse(sql).unwrap() {
+            Statement::CreateTable(create) => create,
+            other => panic!("expected a create table, got {other:?}"),
+        }
+    }
+
+    fn parse_insert(sql: &str) -> Insert {
+        match parse(sql).unwrap() {
+            Statement::Insert(insert) => insert,
+            other => panic!("expected an insert, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn column_types_follow_the_declared_type_names() {
+        let create = parse_create(
+            "CREATE TABLE t (a INTEGER PRIMARY KEY, b DOUBLE, c VARCHAR(10), d, e BLOB, f DECIMAL(10,2), g CHARINT)",
+        );
+        let schema = Schema::new(&create).unwrap();
+        let types: Vec<_> = schema.columns.iter().map(|c| c.ty).collect();
+        assert_eq!(
+            types,
+            [
+                ColumnType::Integer,
+                ColumnType::Real,
+                ColumnType::Text,
+                ColumnType::Blob,
+                ColumnType::Blob,
+                ColumnType::Numeric,
+                ColumnType::Integer,
+            ]
+        );
+        assert_eq!(schema.key, Some(0));
+
+        let duplicate = parse_create("CREATE TABLE t (a, A)");
+        assert_eq!(Schema::new(&duplicate), Err(Error::AlreadyExists(Object::Column, String::from("A"))));
+    }
+
+    #[test]
+    fn stored_sql_parses_back_to_the_same_definition() {
+        let create = parse_create(
+            "CREATE TABLE \"my table\" (\"select\" INT NOT NULL DEFAULT -1 CHECK (\"select\" BETWEEN -1 AND 10 OR \"select\" IN (20, 30)), note TEXT DEFAULT 'it''s')",
+        );
+        let schema = Schema::new(&create).unwrap();
+        assert_eq!(parse_create(&schema.sql()), create);
+
+        // Reals no literal can spell are written so they still parse.
+        let mut create = parse_create("CREATE TABLE t (a REAL DEFAULT 0.5, b REAL DEFAULT 0.5, c REAL DEFAULT 0.5)");
+        for (def, n) in create.columns.iter_mut().zip([f64::INFINITY, f64::NEG_INFINITY, f64::NAN]) {
+            def.constraints = vec![ColumnConstraint::Default(Expr::Literal(Literal::Real(n)))];
+        }
+        let schema = Schema::from_sql(&Schema::new(&create).unwrap().sql()).unwrap();
+        let defaults: Vec<Value> = schema.columns.iter().map(|column| column.default_value()).collect();
+        assert_eq!(defaults, [Value::Real(f64::INFINITY), Value::Real(f64::NEG_INFINITY), Value::Null]);
+    }
+
+    #[test]
+    fn tables_are_recorded_in_the_catalog_and_opened_by_name() {
+        let test_database_name = String::from("test_db_create_table");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        db.create_table(&parse_create("CREATE TABLE items (sku TEXT, price REAL, qty INTEGER)"))
+            .unwrap();
+        db.create_table(&parse_create("CREATE TABLE tags (id INTEGER PRIMARY KEY, label TEXT)"))
+            .unwrap();
+
+        let mut table = db.table("items").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        insert(&mut cur, &parse_insert("INSERT INTO items VALUES ('apple', 1.25, 3), ('pear', 2, NULL)")).unwrap();
+        let mut table = db.table("tags").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        insert(&mut cur, &parse_insert("INSERT INTO tags VALUES (4, 'red')")).unwrap();
+        assert_eq!(
+            insert(&mut cur, &parse_insert("INSERT INTO tags VALUES ('four', 'blue')")),
+            Err(Constraint::Datatype { column: String::from("id") }.into())
+        );
+        let mut table = db.table("users").unwrap();
+        insert(&mut Cursor::new(&mut table), &parse_insert("INSERT INTO users VALUES (1, 'ann', 'ann@x')")).unwrap();
+        db.commit().unwrap();
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let names: Vec<_> = db.catalog.iter().map(|entry| (entry.kind.as_str(), entry.name.as_str())).collect();
+        assert_eq!(names, [("table", "users"), ("table", "items"), ("table", "tags")]);
+        assert_eq!(
+            db.create_table(&parse_create("CREATE TABLE Items (x)")),
+            Err(Error::AlreadyExists(Object::Table, String::from("Items")))
+        );
+        assert_eq!(db.create_table(&parse_create("CREATE TABLE IF NOT EXISTS items (x)")), Ok(()));
+        assert!(db.table("nope").is_err());
+        assert!(db.writable_table(CATALOG_TABLE_NAME).is_err());
+
+        let mut table = db.table("items").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        // Without an INTEGER PRIMARY KEY rows get rowids counting from 1.
+        assert_eq!(
+            fetch_row(&mut cur, 1).unwrap().values,
+            [Value::Text(String::from("apple")), Value::Real(1.25), Value::Integer(3)]
+        );
+        assert_eq!(
+            fetch_row(&mut cur, 2).unwrap().values,
+            [Value::Text(String::from("pear")), Value::Real(2.0), Value::Null]
+        );
+        let mut table = db.table("tags").unwrap();
+        assert_eq!(fetch_row(&mut Cursor::new(&mut table), 4).unwrap().values[1], Value::Text(String::from("red")));
+
+        let mut catalog = db.table(CATALOG_TABLE_NAME).unwrap();
+        let tags = fetch_row(&mut Cursor::new(&mut catalog), 3).unwrap();
+        assert_eq!(
+            tags.values[4],
+            Value::Text(String::from("CREATE TABLE tags (id INTEGER PRIMARY KEY, label TEXT)"))
+        );
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    fn alter(db: &mut Database, sql: &str) -> Result<()> {
+        match parse(sql).unwrap() {
+            Statement::AlterTable(alter) => db.alter_table(&alter),
+            other => panic!("expected an alter table, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn altered_tables_keep_their_rows() {
+        let test_database_name = String::from("test_db_alter_table");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        db.create_table(&parse_create("CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT, age INT CHECK (age >= 0))"))
+            .unwrap();
+        let mut table = db.table("people").unwrap();
+        insert(&mut Cursor::new(&mut table), &parse_insert("INSERT INTO people VALUES (1, 'ann', 30), (2, 'bob', 40)"))
+            .unwrap();
+
+        // Rows stored before a column was added read its default.
+        alter(&mut db, "ALTER TABLE people ADD COLUMN city TEXT DEFAULT 'nowhere'").unwrap();
+        let mut table = db.table("people").unwrap();
+        assert_eq!(fetch_row(&mut Cursor::new(&mut table), 2).unwrap().values[3], Value::Text(String::from("nowhere")));
+        assert!(matches!(
+            alter(&mut db, "ALTER TABLE people ADD code UNIQUE"),
+            Err(Error::Misuse(_))
+        ));
+        assert!(matches!(
+            alter(&mut db, "ALTER TABLE people ADD zip NOT NULL"),
+            Err(Error::Misuse(_))
+        ));
+        assert_eq!(
+            alter(&mut db, "ALTER TABLE people ADD City"),
+            Err(Error::AlreadyExists(Object::Column, String::from("City")))
+        );
+        // The rows already there take the default, which has to pass the
+        // new column's CHECK in each of them.
+        assert_eq!(
+            alter(&mut db, "ALTER TABLE people ADD rank INT DEFAULT 0 CHECK (rank > 0)"),
+            Err(Error::Constraint(Constraint::Check { column: String::from("rank"), check: String::from("rank > 0") }))
+        );
+        assert!(matches!(
+            alter(&mut db, "ALTER TABLE people ADD limit_age INT DEFAULT 35 CHECK (age < limit_age)"),
+            Err(Error::Constraint(Constraint::Check { .. }))
+        ));
+        assert!(db.table("people").unwrap().schema.column_index("rank").is_none());
+        db.create_table(&parse_create("CREATE TABLE empty (id INTEGER PRIMARY KEY)")).unwrap();
+        alter(&mut db, "ALTER TABLE empty ADD rank INT DEFAULT 0 CHECK (rank > 0)").unwrap();
+        db.drop_table("empty", false).unwrap();
+
+        // Checks follow a renamed column.
+        alter(&mut db, "ALTER TABLE people RENAME COLUMN age TO years").unwrap();
+        let mut table = db.table("people").unwrap();
+        assert!(matches!(
+            insert(&mut Cursor::new(&mut table), &parse_insert("INSERT INTO people (years) VALUES (-1)")),
+            Err(Error::Constraint(Constraint::Check { .. }))
+        ));
+
+        alter(&mut db, "ALTER TABLE people RENAME TO folks").unwrap();
+        assert!(db.table("people").is_err());
+        assert_eq!(alter(&mut db, "ALTER TABLE folks RENAME TO users"), Err(Error::AlreadyExists(Object::Table, String::from("users"))));
+
+        alter(&mut db, "ALTER TABLE folks DROP COLUMN name").unwrap();
+        assert_eq!(
+            alter(&mut db, "ALTER TABLE folks DROP id"),
+            Err(Error::Misuse(String::from("Cannot drop column id: it is the PRIMARY KEY.")))
+        );
+        assert_eq!(alter(&mut db, "ALTER TABLE folks DROP name"), Err(Error::NotFound(Object::Column, String::from("name"))));
+        db.commit().unwrap();
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        assert_eq!(
+            db.catalog[1].sql,
+            "CREATE TABLE folks (id INTEGER PRIMARY KEY, years INT CHECK (years >= 0), city TEXT DEFAULT ('nowhere'))"
+        );
+        let mut table = db.table("folks").unwrap();
+        assert_eq!(
+            fetch_row(&mut Cursor::new(&mut table), 1).unwrap().values,
+            [Value::Integer(1), Value::Integer(30), Value::Text(String::from("nowhere"))]
+        );
+        let mut catalog = db.table(CATALOG_TABLE_NAME).unwrap();
+        assert_eq!(fetch_row(&mut Cursor::new(&mut catalog), 2).unwrap().values[1], Value::Text(String::from("folks")));
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn dropped_tables_hand_their_pages_to_the_freelist() {
+        let test_database_name = String::from("test_db_drop_table");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        db.create_table(&parse_create("CREATE TABLE scratch (id INTEGER PRIMARY KEY, body TEXT)")).unwrap();
+        let mut table = db.table("scratch").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        for id in 1..=20 {
+            // Long enough to spill into overflow pages.
+            let sql = format!("INSERT INTO scratch VALUES ({id}, '{}')", "x".repeat(2000));
+            insert(&mut cur, &parse_insert(&sql)).unwrap();
+        }
+        let used = cur.table.pages().unwrap().len();
+        let page_count = db.pager.stats().page_count;
+
+        db.drop_table("scratch", false).unwrap();
+        assert_eq!(db.pager.stats().free_pages, used);
+        assert!(db.table("scratch").is_err());
+        assert_eq!(db.drop_table("scratch", false), Err(Error::NotFound(Object::Table, String::from("scratch"))));
+        assert_eq!(db.drop_table("scratch", true), Ok(()));
+        assert!(matches!(db.drop_table(CATALOG_TABLE_NAME, true), Err(Error::Misuse(_))));
+        db.commit().unwrap();
+        drop(db);
+
+        // The freelist survives a reopen and is used before the file grows.
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        assert_eq!(db.pager.stats().free_pages, used);
+        db.create_table(&parse_create("CREATE TABLE scratch (id INTEGER PRIMARY KEY, body TEXT)")).unwrap();
+        let mut table = db.table("scratch").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        insert(&mut cur, &parse_insert("INSERT INTO scratch VALUES (1, 'short')")).unwrap();
+        assert_eq!(fetch_row(&mut cur, 1).unwrap().values[1], Value::Text(String::from("short")));
+        assert_eq!(db.pager.stats().free_pages, used - 2);
+        assert_eq!(db.pager.stats().page_count, page_count);
+        let names: Vec<_> = db.catalog.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["users", "scratch"]);
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn dropped_indexes_hand_their_pages_to_the_freelist() {
+        let test_database_name = String::from("test_db_drop_index");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_table(&parse_create("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)")).unwrap();
+        // Nothing creates indexes yet, so their catalog rows are written by
+        // hand, each with an empty root page of its own.
+        let text = |s: &str| Value::Text(s.to_string());
+        for (rowid, name) in [(10, "notes_body"), (11, "notes_id")] {
+            let root_page = db.pager.allocate_page().unwrap();
+            let schema = Schema::from_sql(CATALOG_SCHEMA).unwrap();
+            let mut catalog = Table::open(&mut db.pager, schema, SCHEMA_ROOT_PAGE).unwrap();
+            let sql = format!("CREATE INDEX {name} ON notes (body)");
+            let values = [text("index"), text(name), text("notes"), Value::Integer(root_page as i64), text(&sql)];
+            insert_row(&mut Cursor::new(&mut catalog), rowid, &values).unwrap();
+        }
+        db.commit().unwrap();
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        let err = db.drop_index("nope", false).unwrap_err();
+        assert_eq!(err, Error::NotFound(Object::Index, String::from("nope")));
+        assert_eq!(err.to_string(), "No such index: nope.");
+        assert_eq!(db.drop_index("nope", true), Ok(()));
+        assert_eq!(db.drop_index("notes", false), Err(Error::NotFound(Object::Index, String::from("notes"))));
+        db.drop_index("NOTES_BODY", false).unwrap();
+        assert_eq!(db.pager.stats().free_pages, 1);
+
+        // Dropping the table drops the index still on it, and the catalog
+        // leaf they leave empty is freed with them.
+        let table_pages = db.table("notes").unwrap().pages().unwrap().len();
+        db.drop_table("notes", false).unwrap();
+        assert_eq!(db.pager.stats().free_pages, 1 + table_pages + 1 + 1);
+        db.commit().unwrap();
+        drop(db);
+
+        let db = Database::open(&test_database_name).unwrap();
+        assert!(db.catalog.is_empty());
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+}
@@ -1,59 +1,437 @@
+use crate::ast::{BinaryOp, Delete, Expr, Insert, Literal, ResultColumn, Select, Update};
 use crate::cursor::Cursor;
-use crate::table::{fetch_row, insert_row};
+use crate::error::{Constraint, Error, Object, Result};
+use crate::eval;
+use crate::schema::Schema;
+use crate::table::{cursor_row, delete_row, fetch_row, insert_row, Row};
+use crate::value::Value;
+use std::cmp::Ordering;
 
-#[derive(Debug, PartialEq)]
-pub enum InsertError {
-    NotEnoughArgs { got: usize, expected: usize },
-    FlushError
-}
+/// Inserts every row of `statement` and returns the rowid of the last one.
+pub fn insert(curr: &mut Cursor, statement: &Insert) -> Result<i64> {
+    let schema = curr.table.schema.clone();
+    if !statement.table.eq_ignore_ascii_case(&schema.name) {
+        return Err(Error::not_found(Object::Table, &statement.table));
+    }
+    // Where each of the statement's values goes; without a column list the
+    // values are in table order.
+    let targets = if statement.columns.is_empty() {
+        (0..schema.columns.len()).collect()
+    } else {
+        statement
+            .columns
+            .iter()
+            .map(|column| schema.column_index(column).ok_or_else(|| Error::not_found(Object::Column, column)))
+            .collect::<Result<Vec<_>>>()?
+    };
 
-#[derive(Debug)]
-pub enum SelectError {
-    NotEnoughArgs { got: usize, expected: usize },
-    OutOfBounds,
+    let mut last_key = 0;
+    for exprs in &statement.values {
+        let expected = targets.len();
+        if exprs.len() != expected {
+            return Err(Error::misuse(format!(
+                "Expected {expected} value(s) to insert, got {}.",
+                exprs.len()
+            )));
+        }
+
+        // Columns left out take their default, or NULL without one.
+        let constant = |expr: &Expr| eval::evaluate(expr, &|_| None);
+        let mut values = schema
+            .columns
+            .iter()
+            .map(|column| column.default.as_ref().map_or(Ok(Value::Null), constant))
+            .collect::<Result<Vec<_>>>()?;
+        for (&target, expr) in targets.iter().zip(exprs) {
+            values[target] = constant(expr)?;
+        }
+        let mut values: Vec<Value> = values
+            .into_iter()
+            .zip(&schema.columns)
+            .map(|(value, column)| value.with_affinity(column.ty))
+            .collect();
+
+        // The key column picks the rowid. Left out, the row is given the
+        // next one, as it always is in tables without a key column.
+        let key = match schema.key.map(|key| (key, &values[key])) {
+            Some((_, Value::Integer(n))) => *n,
+            Some((key, Value::Null)) => {
+                let rowid = curr.table.next_rowid()?;
+                values[key] = Value::Integer(rowid);
+                rowid
+            }
+            Some((key, _)) => {
+                return Err(Constraint::Datatype {
+                    column: schema.columns[key].name.clone(),
+                }
+                .into());
+            }
+            None => curr.table.next_rowid()?,
+        };
+        check_constraints(curr, key, &values, true)?;
+        insert_row(curr, key, &values)?;
+        last_key = key;
+    }
+    Ok(last_key)
 }
 
-pub fn insert(curr: &mut Cursor,cmd: &str) -> Result<i32, InsertError> {
-    let row_to_insert: Vec<&str> = cmd
-        .strip_prefix("insert ")
-        .unwrap()
-        .split_ascii_whitespace()
+/// Checks a row about to be stored under `key` against the NOT NULL, CHECK
+/// and UNIQUE constraints of its columns, and a `new` row against the rows
+/// already stored under its key. A NULL passes a CHECK and never clashes
+/// with another row.
+fn check_constraints(curr: &mut Cursor, key: i64, values: &[Value], new: bool) -> Result<()> {
+    let schema = curr.table.schema.clone();
+    for (column, value) in schema.columns.iter().zip(values) {
+        if column.not_null && value.is_null() {
+            return Err(Constraint::NotNull {
+                column: column.name.clone(),
+            }
+            .into());
+        }
+    }
+
+    let lookup = |name: &str| schema.column_index(name).map(|index| values[index].clone());
+    for column in &schema.columns {
+        for check in &column.checks {
+            let result = eval::evaluate(check, &lookup)?;
+            if result.truth() == Some(false) {
+                return Err(Constraint::Check {
+                    column: column.name.clone(),
+                    check: check.to_string(),
+                }
+                .into());
+            }
+        }
+    }
+
+    // The rowid is answered by a seek.
+    if new && row_exists(curr, key)? {
+        let column = schema.key_column().map_or("rowid", |column| &column.name);
+        return Err(Constraint::Unique {
+            column: column.to_string(),
+        }
+        .into());
+    }
+
+    // Other unique columns have no index to seek in yet, so each of them
+    // costs a scan of the whole table: O(n) per row written, O(n²) for a
+    // bulk insert into a table with one.
+    let unique: Vec<usize> = (0..schema.columns.len())
+        .filter(|&index| schema.columns[index].unique && schema.key != Some(index) && !values[index].is_null())
         .collect();
-    if row_to_insert.len() < 3usize {
-        Err(InsertError::NotEnoughArgs {
-            got: row_to_insert.len(),
-            expected: 3,
-        })
-    } else {
-        let id: i32 = row_to_insert[0].parse::<i32>().unwrap();
-        insert_row(curr, id, row_to_insert[1], row_to_insert[2]);
-        Ok(id)
+    if unique.is_empty() {
+        return Ok(());
     }
+    let mut cursor = Cursor::new(curr.table);
+    while !cursor.end_of_table() {
+        let Some(row) = cursor_row(&mut cursor)? else {
+            break;
+        };
+        if row.key != key
+            && let Some(&index) = unique
+                .iter()
+                .find(|&&index| row.values[index].compare(&values[index]) == Some(Ordering::Equal))
+        {
+            return Err(Constraint::Unique {
+                column: schema.columns[index].name.clone(),
+            }
+            .into());
+        }
+        cursor.advance();
+    }
+    Ok(())
 }
 
-pub fn select(curr: &mut Cursor, cmd: &str) -> Result<i32, SelectError> {
-    let row_to_select: Vec<&str> = cmd
-        .strip_prefix("select ")
-        .unwrap()
-        .split_ascii_whitespace()
-        .collect();
-    if row_to_select.len() < 1usize {
-        return Err(SelectError::NotEnoughArgs {
-            got: row_to_select.len(),
-            expected: 1,
+/// Runs a select and returns the rows it matches, in rowid order.
+pub fn select(curr: &mut Cursor, statement: &Select) -> Result<Vec<Row>> {
+    let schema = curr.table.schema.clone();
+    match &statement.from {
+        Some(table) if table.eq_ignore_ascii_case(&schema.name) => {}
+        Some(table) => return Err(Error::not_found(Object::Table, table)),
+        None => return Err(unsupported("SELECT without FROM")),
+    }
+    // A result column naming no column of the table fails even when no
+    // row matches. Against a row of NULLs, any other one can be evaluated.
+    let nulls = Row {
+        key: 0,
+        values: vec![Value::Null; schema.columns.len()],
+    };
+    result_row(statement, &schema, &nulls)?;
+
+    let access = plan(statement.where_clause.as_ref(), &schema);
+    matching_rows(curr, &access, statement.where_clause.as_ref())
+}
+
+/// The names of the columns `statement` returns from a table of `schema`:
+/// those of the table for a `*`, the expression itself for any other.
+pub fn result_columns(statement: &Select, schema: &Schema) -> Vec<String> {
+    let mut columns = vec![];
+    for column in &statement.columns {
+        match column {
+            ResultColumn::Star => columns.extend(schema.columns.iter().map(|column| column.name.clone())),
+            ResultColumn::Expr(expr) => columns.push(expr.to_string()),
+        }
+    }
+    columns
+}
+
+/// The values `statement` returns for one of the rows it matched, a value
+/// per result column.
+pub fn result_row(statement: &Select, schema: &Schema, row: &Row) -> Result<Vec<Value>> {
+    let mut values = vec![];
+    for column in &statement.columns {
+        match column {
+            ResultColumn::Star => values.extend(row.values.iter().cloned()),
+            ResultColumn::Expr(expr) => values.push(eval::evaluate(expr, &|name| row_column(schema, row, name))?),
+        }
+    }
+    Ok(values)
+}
+
+/// Whether `statement` asks for rows by their rowids, as `WHERE id = 2`
+/// does, rather than for whatever rows match.
+pub fn looks_up_rowids(statement: &Select, schema: &Schema) -> bool {
+    matches!(plan(statement.where_clause.as_ref(), schema), Access::Ids(_))
+}
+
+/// Runs a select without a FROM clause, whose result columns only involve
+/// constants and functions such as `last_insert_rowid()`. Returns the one
+/// row, or nothing if the WHERE clause doesn't hold.
+pub fn select_without_table(statement: &Select, last_insert_rowid: i64) -> Result<Vec<Value>> {
+    let function = |name: &str, args: &[Value]| match name.to_ascii_lowercase().as_str() {
+        "last_insert_rowid" if args.is_empty() => Some(Value::Integer(last_insert_rowid)),
+        _ => None,
+    };
+    let evaluate = |expr: &Expr| eval::evaluate_with(expr, &|_| None, &function);
+
+    if let Some(expr) = &statement.where_clause
+        && evaluate(expr)?.truth() != Some(true)
+    {
+        return Ok(vec![]);
+    }
+    let mut values = vec![];
+    for column in &statement.columns {
+        let ResultColumn::Expr(expr) = column else {
+            return Err(unsupported("SELECT * without FROM"));
+        };
+        values.push(evaluate(expr)?);
+    }
+    Ok(values)
+}
+
+/// Rewrites the rows matching the statement's WHERE clause in place and
+/// returns how many there were.
+pub fn update(curr: &mut Cursor, statement: &Update) -> Result<usize> {
+    let schema = curr.table.schema.clone();
+    if !statement.table.eq_ignore_ascii_case(&schema.name) {
+        return Err(Error::not_found(Object::Table, &statement.table));
+    }
+    let mut assignments = Vec::with_capacity(statement.assignments.len());
+    for (column, expr) in &statement.assignments {
+        let index = schema
+            .column_index(column)
+            .ok_or_else(|| Error::not_found(Object::Column, column))?;
+        // Rows are stored under their id, so the id can't be changed.
+        if schema.key == Some(index) {
+            return Err(Error::misuse("The id of a row cannot be changed."));
+        }
+        assignments.push((index, expr));
+    }
+
+    let access = plan(statement.where_clause.as_ref(), &schema);
+    let rows = matching_rows(curr, &access, statement.where_clause.as_ref())?;
+    for row in &rows {
+        // Every assignment sees the row as it was before the update.
+        let mut values = row.values.clone();
+        for &(index, expr) in &assignments {
+            let value = eval::evaluate(expr, &|name| row_column(&schema, row, name))?;
+            values[index] = value.with_affinity(schema.columns[index].ty);
+        }
+        check_constraints(curr, row.key, &values, false)?;
+        insert_row(curr, row.key, &values)?;
+    }
+    Ok(rows.len())
+}
+
+/// Removes the rows matching the statement's WHERE clause and returns how
+/// many there were.
+pub fn delete(curr: &mut Cursor, statement: &Delete) -> Result<usize> {
+    let schema = curr.table.schema.clone();
+    if !statement.table.eq_ignore_ascii_case(&schema.name) {
+        return Err(Error::not_found(Object::Table, &statement.table));
+    }
+
+    let access = plan(statement.where_clause.as_ref(), &schema);
+    let rows = matching_rows(curr, &access, statement.where_clause.as_ref())?;
+    for row in &rows {
+        delete_row(curr, row.key)?;
+    }
+    Ok(rows.len())
+}
+
+/// Fetches the rows `access` leads to that satisfy `where_clause`.
+fn matching_rows(curr: &mut Cursor, access: &Access, where_clause: Option<&Expr>) -> Result<Vec<Row>> {
+    let schema = curr.table.schema.clone();
+    let matching = |row: &Row| match where_clause {
+        Some(expr) => eval::matches(expr, &|name| row_column(&schema, row, name)),
+        None => Ok(true),
+    };
+
+    let mut rows = vec![];
+    match *access {
+        Access::Ids(ref ids) => {
+            // Rows are stored under their key, so each id is a single lookup.
+            for &id in ids {
+                let row = match fetch_row(curr, id) {
+                    Ok(row) => row,
+                    Err(Error::NotFound(..)) => continue,
+                    Err(err) => return Err(err),
+                };
+                if matching(&row)? {
+                    rows.push(row);
+                }
+            }
+        }
+        Access::Range { start, end } => {
+            let mut cursor = Cursor::at(curr.table, start)?;
+            while !cursor.end_of_table() {
+                let Some(row) = cursor_row(&mut cursor)? else {
+                    break;
+                };
+                if row.key > end {
+                    break;
+                }
+                if matching(&row)? {
+                    rows.push(row);
+                }
+                cursor.advance();
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Whether a row is stored under `key`.
+fn row_exists(curr: &mut Cursor, key: i64) -> Result<bool> {
+    match fetch_row(curr, key) {
+        Ok(_) => Ok(true),
+        Err(Error::NotFound(..)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::misuse(format!("{what} is not supported yet."))
+}
+
+fn row_column(schema: &Schema, row: &Row, name: &str) -> Option<Value> {
+    match schema.column_index(name) {
+        Some(index) => Some(row.values[index].clone()),
+        None => schema.is_rowid(name).then_some(Value::Integer(row.key)),
+    }
+}
+
+/// Which rows a query has to visit.
+#[derive(Debug, PartialEq)]
+enum Access {
+    /// Direct lookups of the given rowids.
+    Ids(Vec<i64>),
+    /// A cursor walk over the rowids in `start..=end`.
+    Range { start: i64, end: i64 },
+}
+
+/// Narrows the rows to visit using the rowid constraints among the
+/// top-level `AND`ed terms of `where_clause`. Every visited row is still
+/// checked against the whole clause.
+fn plan(where_clause: Option<&Expr>, schema: &Schema) -> Access {
+    let id_column = |expr: &Expr| matches!(expr, Expr::Column(column) if schema.is_rowid(column));
+
+    let mut terms = vec![];
+    if let Some(expr) = where_clause {
+        conjuncts(expr, &mut terms);
+    }
+
+    // Bounds are kept wider than a rowid so `id < i64::MIN` can be empty.
+    let (mut start, mut end) = (i64::MIN as i128, i64::MAX as i128);
+    let mut ids: Option<Vec<i64>> = None;
+    let mut only = |candidates: Vec<i64>| {
+        ids = Some(match ids.take() {
+            Some(ids) => ids.into_iter().filter(|id| candidates.contains(id)).collect(),
+            None => candidates,
         });
-    } else {
-        let id: i32 = row_to_select[0].parse::<i32>().unwrap();
-        match fetch_row(curr, id) {
-            Ok(row) => {
-                let name = std::str::from_utf8(&row.name).unwrap();
-                let email = std::str::from_utf8(&row.email).unwrap();
-                let id = row.id;
-                println!("ID: {id}; name: {name}; email: {email}");
-                return Ok(id);
+    };
+    for term in terms {
+        match term {
+            Expr::Binary { op, left, right } => {
+                let (op, n) = match (id_column(left), integer(right), integer(left), id_column(right)) {
+                    (true, Some(n), _, _) => (*op, n),
+                    (_, _, Some(n), true) => (flip(*op), n),
+                    _ => continue,
+                };
+                let n = n as i128;
+                match op {
+                    BinaryOp::Eq => only(vec![n as i64]),
+                    BinaryOp::Lt => end = end.min(n - 1),
+                    BinaryOp::LtEq => end = end.min(n),
+                    BinaryOp::Gt => start = start.max(n + 1),
+                    BinaryOp::GtEq => start = start.max(n),
+                    _ => {}
+                }
             }
-            Err(_) => print!("Row does not exist."),
+            Expr::In { expr, list, negated: false } if id_column(expr) => {
+                if let Some(list) = list.iter().map(integer).collect() {
+                    only(list);
+                }
+            }
+            Expr::Between { expr, low, high, negated: false } if id_column(expr) => {
+                if let (Some(low), Some(high)) = (integer(low), integer(high)) {
+                    start = start.max(low as i128);
+                    end = end.min(high as i128);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match ids {
+        Some(mut ids) => {
+            ids.retain(|&id| (start..=end).contains(&(id as i128)));
+            ids.sort_unstable();
+            ids.dedup();
+            Access::Ids(ids)
+        }
+        None if start > end => Access::Range { start: 0, end: -1 },
+        None => Access::Range {
+            start: start.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+            end: end.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        },
+    }
+}
+
+fn conjuncts<'a>(expr: &'a Expr, out: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::Binary { op: BinaryOp::And, left, right } => {
+            conjuncts(left, out);
+            conjuncts(right, out);
         }
+        expr => out.push(expr),
+    }
+}
+
+fn integer(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Literal(Literal::Integer(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+/// The operator that gives the same result with the operands swapped.
+fn flip(op: BinaryOp) -> BinaryOp {
+    match op {
+        BinaryOp::Lt => BinaryOp::Gt,
+        BinaryOp::LtEq => BinaryOp::GtEq,
+        BinaryOp::Gt => BinaryOp::Lt,
+        BinaryOp::GtEq => BinaryOp::LtEq,
+        op => op,
     }
-    Ok(0)
 }
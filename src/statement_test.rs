@@ -1,40 +1,360 @@
 #[cfg(test)]
 mod tests {
     use std::fs;
+    use crate::ast::{Delete, Insert, Select, Statement, Update};
     use crate::cursor::Cursor;
-    use crate::statement::{InsertError, insert, select};
-    use crate::table::Table;
+    use crate::parser::parse;
+    use crate::error::{Constraint, Error, Object};
+    use crate::statement::{delete, insert, select, update};
+    use crate::table::Row;
+    use crate::database::Database;
+    use crate::value::Value;
+
+    fn text(s: impl Into<String>) -> Value {
+        Value::Text(s.into())
+    }
+
+    fn parse_insert(sql: &str) -> Insert {
+        match parse(sql).unwrap() {
+            Statement::Insert(insert) => insert,
+            other => panic!("expected an insert, got {other:?}"),
+        }
+    }
+
+    fn parse_update(sql: &str) -> Update {
+        match parse(sql).unwrap() {
+            Statement::Update(update) => update,
+            other => panic!("expected an update, got {other:?}"),
+        }
+    }
+
+    fn parse_delete(sql: &str) -> Delete {
+        match parse(sql).unwrap() {
+            Statement::Delete(delete) => delete,
+            other => panic!("expected a delete, got {other:?}"),
+        }
+    }
+
+    fn parse_select(sql: &str) -> Select {
+        match parse(sql).unwrap() {
+            Statement::Select(select) => select,
+            other => panic!("expected a select, got {other:?}"),
+        }
+    }
+
+    fn keys(rows: Vec<Row>) -> Vec<i64> {
+        rows.iter().map(|row| row.key).collect()
+    }
 
     #[test]
     fn insert_returns_error_if_not_enough_args() {
         let test_database_name = String::from("test_db");
-        let mut table = Table::db_open(&test_database_name).unwrap();
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
         let curr = &mut Cursor::new(&mut table);
-        let res = insert(curr,"insert 1 alice");
-        assert!(matches!(
-            res,
-            Err(InsertError::NotEnoughArgs {
-                got: 2,
-                expected: 3
-            })
-        ));
+        let res = insert(curr, &parse_insert("INSERT INTO users VALUES (1, 'alice')"));
+        assert_eq!(res, Err(Error::Misuse(String::from("Expected 3 value(s) to insert, got 2."))));
+        let res = insert(curr, &parse_insert("INSERT INTO users VALUES ('abc', 'x', 'y')"));
+        assert_eq!(res, Err(Constraint::Datatype { column: String::from("id") }.into()));
     }
 
     #[test]
     fn insert_returns_id_on_success() {
         let test_database_name = String::from("test_db");
-        let mut table = Table::db_open(&test_database_name).unwrap();
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
         let mut cur = Cursor::new(&mut table);
 
-        let res = insert(&mut cur, "insert 42 alice alice");
-        cur.table.db_close().unwrap();
+        let res = insert(&mut cur, &parse_insert("INSERT INTO users VALUES (42, 'alice', 'alice')"));
+        cur.table.pager.commit().unwrap();
 
         assert_eq!(res, Ok(42));
 
-        let output = select(&mut cur, "select 42").unwrap();
-        assert_eq!(output, 42i32);
+        let output = select(&mut cur, &parse_select("SELECT * FROM users WHERE id = 42")).unwrap();
+        assert_eq!(keys(output), [42]);
 
-        drop(cur);
         fs::remove_file("test_db").unwrap();
     }
+
+    #[test]
+    fn sql_insert_maps_values_by_column_name() {
+        let test_database_name = String::from("test_db_sql_insert");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+
+        let res = insert(
+            &mut cur,
+            &parse_insert("INSERT INTO users (email, id, name) VALUES ('a b@example.com', 7, 'Ann, Jr.')"),
+        );
+        assert_eq!(res, Ok(7));
+        let row = crate::table::fetch_row(&mut cur, 7).unwrap();
+        assert_eq!(row.values[1], text("Ann, Jr."));
+        assert_eq!(row.values[2], text("a b@example.com"));
+
+        let res = insert(&mut cur, &parse_insert("INSERT INTO people VALUES (1, 'x', 'y')"));
+        assert_eq!(res, Err(Error::NotFound(Object::Table, String::from("people"))));
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn full_scan_visits_written_rows_in_key_order() {
+        let test_database_name = String::from("test_db_scan");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        for sql in [
+            "INSERT INTO users VALUES (40, 'carol', 'carol')",
+            "INSERT INTO users VALUES (3, 'bob', 'bob')",
+            "INSERT INTO users VALUES (1, 'alice', 'alice')",
+        ] {
+            insert(&mut cur, &parse_insert(sql)).unwrap();
+        }
+
+        let mut cursor = Cursor::new(cur.table);
+        let mut ids = vec![];
+        while !cursor.end_of_table() {
+            if let Some(row) = crate::table::cursor_row(&mut cursor).unwrap() {
+                ids.push(row.key);
+            }
+            cursor.advance();
+        }
+        assert_eq!(ids, vec![1, 3, 40]);
+
+        let mut cur = Cursor::new(&mut table);
+        assert_eq!(keys(select(&mut cur, &parse_select("SELECT * FROM users")).unwrap()), [1, 3, 40]);
+        assert_eq!(keys(select(&mut cur, &parse_select("SELECT * FROM users")).unwrap()), [1, 3, 40]);
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn where_clauses_filter_scans_and_id_lookups() {
+        let test_database_name = String::from("test_db_where");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        for sql in [
+            "INSERT INTO users VALUES (2, 'bob', 'bob@x')",
+            "INSERT INTO users VALUES (5, 'carol', 'carol@y')",
+            "INSERT INTO users VALUES (9, 'dave', 'dave@x')",
+            "INSERT INTO users VALUES (30, 'erin', 'erin@x')",
+        ] {
+            insert(&mut cur, &parse_insert(sql)).unwrap();
+        }
+
+        let mut matching = |clause: &str| {
+            keys(select(&mut cur, &parse_select(&format!("SELECT * FROM users WHERE {clause}"))).unwrap())
+        };
+        assert_eq!(matching("email = 'carol@y' OR name = 'bob'"), [2, 5]);
+        assert_eq!(matching("id BETWEEN 2 AND 20 AND email <> 'carol@y'"), [2, 9]);
+        assert_eq!(matching("id IN (5, 30, 31) AND name <> 'erin'"), [5]);
+        assert_eq!(matching("id > 3 AND id * 2 < 20 AND NOT name = 'carol'"), [9]);
+        assert_eq!(matching("2 < id AND id <= 30"), [5, 9, 30]);
+        assert_eq!(matching("id = 9 AND id = 5"), []);
+        assert!(matches!(
+            select(&mut cur, &parse_select("SELECT * FROM users WHERE age = 3")),
+            Err(Error::NotFound(Object::Column, _))
+        ));
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn update_rewrites_matching_rows_and_persists_on_commit() {
+        let test_database_name = String::from("test_db_update");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        for sql in [
+            "INSERT INTO users VALUES (1, 'ann', 'ann@x')",
+            "INSERT INTO users VALUES (2, 'bob', 'bob@x')",
+            "INSERT INTO users VALUES (3, 'cat', 'cat@y')",
+        ] {
+            insert(&mut cur, &parse_insert(sql)).unwrap();
+        }
+        cur.table.pager.commit().unwrap();
+
+        let long_email = "z".repeat(600);
+        let sql = format!("UPDATE users SET email = '{long_email}', name = name || '!' WHERE email <> 'cat@y'");
+        assert_eq!(update(&mut cur, &parse_update(&sql)).unwrap(), 2);
+        assert_eq!(update(&mut cur, &parse_update("UPDATE users SET name = 'x' WHERE id = 7")).unwrap(), 0);
+        assert!(matches!(
+            update(&mut cur, &parse_update("UPDATE users SET id = 4")),
+            Err(Error::Misuse(_))
+        ));
+        cur.table.pager.commit().unwrap();
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        let ann = crate::table::fetch_row(&mut cur, 1).unwrap();
+        assert_eq!(ann.values[1..], [text("ann!"), text(long_email)]);
+        let cat = crate::table::fetch_row(&mut cur, 3).unwrap();
+        assert_eq!(cat.values[1..], [text("cat"), text("cat@y")]);
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn deleted_rows_no_longer_exist() {
+        let test_database_name = String::from("test_db_delete");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        for sql in [
+            "INSERT INTO users VALUES (1, 'ann', 'ann@x')",
+            "INSERT INTO users VALUES (2, 'bob', 'bob@x')",
+            "INSERT INTO users VALUES (3, 'cat', 'cat@y')",
+        ] {
+            insert(&mut cur, &parse_insert(sql)).unwrap();
+        }
+
+        assert_eq!(delete(&mut cur, &parse_delete("DELETE FROM users WHERE email = 'ann@x' OR id = 3")).unwrap(), 2);
+        assert_eq!(delete(&mut cur, &parse_delete("DELETE FROM users WHERE id = 3")).unwrap(), 0);
+        cur.table.pager.commit().unwrap();
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        let err = crate::table::fetch_row(&mut cur, 1).unwrap_err();
+        assert_eq!(err, Error::NotFound(Object::Row, String::from("1")));
+        assert_eq!(keys(select(&mut cur, &parse_select("SELECT * FROM users WHERE id = 3")).unwrap()), []);
+        assert_eq!(keys(select(&mut cur, &parse_select("SELECT * FROM users")).unwrap()), [2]);
+
+        assert_eq!(delete(&mut cur, &parse_delete("DELETE FROM users")).unwrap(), 1);
+        assert!(crate::table::fetch_row(&mut cur, 2).is_err());
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn empty_rows_are_told_apart_from_missing_ones() {
+        let test_database_name = String::from("test_db_empty_row");
+        let mut db = Database::open(&test_database_name).unwrap();
+        db.create_users().unwrap();
+        let mut table = db.table("users").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        let empty = vec![Value::Integer(0), text(""), text("")];
+        crate::table::insert_row(&mut cur, 0, &empty).unwrap();
+
+        let row = crate::table::fetch_row(&mut cur, 0).unwrap();
+        assert_eq!((row.key, row.values), (0, empty));
+        let err = crate::table::fetch_row(&mut cur, 5).unwrap_err();
+        assert!(matches!(err, Error::NotFound(Object::Row, _)));
+        assert_eq!(keys(select(&mut cur, &parse_select("SELECT * FROM users WHERE id = 5")).unwrap()), []);
+        assert_eq!(delete(&mut cur, &parse_delete("DELETE FROM users WHERE name = ''")).unwrap(), 1);
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn constraints_are_enforced_on_insert_and_update() {
+        let test_database_name = String::from("test_db_constraints");
+        let mut db = Database::open(&test_database_name).unwrap();
+        let Ok(Statement::CreateTable(create)) = parse(
+            "CREATE TABLE accounts (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE, \
+             balance INTEGER DEFAULT 10 CHECK (balance >= 0), plan TEXT DEFAULT ('free' || '-tier'))",
+        ) else {
+            panic!("expected a create table");
+        };
+        db.create_table(&create).unwrap();
+        let mut table = db.table("accounts").unwrap();
+        let mut cur = Cursor::new(&mut table);
+
+        insert(&mut cur, &parse_insert("INSERT INTO accounts (id, email) VALUES (1, 'a@x')")).unwrap();
+        let row = crate::table::fetch_row(&mut cur, 1).unwrap();
+        assert_eq!(row.values[2..], [Value::Integer(10), text("free-tier")]);
+        insert(&mut cur, &parse_insert("INSERT INTO accounts VALUES (2, 'b@x', NULL, NULL)")).unwrap();
+
+        let violations = [
+            ("INSERT INTO accounts (id) VALUES (3)", Constraint::NotNull { column: String::from("email") }),
+            ("INSERT INTO accounts (id, email) VALUES (3, 'a@x')", Constraint::Unique { column: String::from("email") }),
+            ("INSERT INTO accounts (id, email) VALUES (1, 'c@x')", Constraint::Unique { column: String::from("id") }),
+            (
+                "INSERT INTO accounts VALUES (3, 'c@x', -5, NULL)",
+                Constraint::Check { column: String::from("balance"), check: String::from("balance >= 0") },
+            ),
+        ];
+        for (sql, expected) in violations {
+            assert_eq!(insert(&mut cur, &parse_insert(sql)), Err(Error::Constraint(expected)), "{sql}");
+        }
+        assert!(crate::table::fetch_row(&mut cur, 3).is_err());
+
+        let err = update(&mut cur, &parse_update("UPDATE accounts SET email = 'a@x' WHERE id = 2")).unwrap_err();
+        assert!(matches!(err, Error::Constraint(Constraint::Unique { ref column }) if column == "email"));
+        let err = update(&mut cur, &parse_update("UPDATE accounts SET balance = balance - 20")).unwrap_err();
+        assert_eq!(err.to_string(), "CHECK constraint failed on balance: balance >= 0.");
+        // A row may keep its own unique value.
+        assert_eq!(update(&mut cur, &parse_update("UPDATE accounts SET email = 'a@x', balance = 0 WHERE id = 1")).unwrap(), 1);
+
+        let Ok(Statement::CreateTable(bad)) = parse("CREATE TABLE bad (a DEFAULT (b + 1))") else {
+            panic!("expected a create table");
+        };
+        assert!(db.create_table(&bad).is_err());
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn rowids_are_assigned_and_autoincrement_never_reuses_them() {
+        let test_database_name = String::from("test_db_rowid");
+        let mut db = Database::open(&test_database_name).unwrap();
+        for sql in [
+            "CREATE TABLE notes (body TEXT)",
+            "CREATE TABLE events (id INTEGER PRIMARY KEY AUTOINCREMENT, what TEXT)",
+        ] {
+            let Ok(Statement::CreateTable(create)) = parse(sql) else {
+                panic!("expected a create table");
+            };
+            db.create_table(&create).unwrap();
+        }
+
+        let mut table = db.table("notes").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        assert_eq!(insert(&mut cur, &parse_insert("INSERT INTO notes VALUES ('a'), ('b'), ('c')")), Ok(3));
+        assert_eq!(delete(&mut cur, &parse_delete("DELETE FROM notes WHERE rowid = 3")).unwrap(), 1);
+        // Plain rowids continue from the highest one left.
+        assert_eq!(insert(&mut cur, &parse_insert("INSERT INTO notes VALUES ('d')")), Ok(3));
+        assert_eq!(keys(select(&mut cur, &parse_select("SELECT * FROM notes WHERE oid > 1 AND body <> 'd'")).unwrap()), [2]);
+
+        let mut table = db.table("events").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        assert_eq!(insert(&mut cur, &parse_insert("INSERT INTO events (what) VALUES ('boot')")), Ok(1));
+        assert_eq!(insert(&mut cur, &parse_insert("INSERT INTO events VALUES (10000000000, 'jump')")), Ok(10_000_000_000));
+        assert_eq!(delete(&mut cur, &parse_delete("DELETE FROM events WHERE id >= 2")).unwrap(), 1);
+        cur.table.pager.commit().unwrap();
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        let mut table = db.table("events").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        // The high-water mark outlives the row that set it.
+        assert_eq!(insert(&mut cur, &parse_insert("INSERT INTO events (what) VALUES ('halt')")), Ok(10_000_000_001));
+        assert_eq!(
+            keys(select(&mut cur, &parse_select("SELECT * FROM events WHERE _rowid_ = 10000000001")).unwrap()),
+            [10_000_000_001]
+        );
+
+        let last = crate::statement::select_without_table(&parse_select("SELECT last_insert_rowid(), 1 + 1"), 7).unwrap();
+        assert_eq!(last, [Value::Integer(7), Value::Integer(2)]);
+        let Ok(Statement::CreateTable(bad)) = parse("CREATE TABLE bad (id INTEGER, name TEXT PRIMARY KEY AUTOINCREMENT)") else {
+            panic!("expected a create table");
+        };
+        assert!(db.create_table(&bad).is_err());
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
 }
@@ -1,192 +1,478 @@
+use crate::error::{Error, Limit, Object, Result};
+use crate::overflow;
 use crate::persistence::Pager;
-use crate::statement::SelectError;
-use std::fs::OpenOptions;
-use std::io::{self, Read, Seek, SeekFrom, Write};
+use crate::record;
 use std::mem::size_of;
-use std::sync::{LazyLock, Mutex};
 use crate::cursor::Cursor;
+use crate::schema::Schema;
+use crate::value::Value;
 
-pub const USERNAME_LEN: usize = 32;
-pub const EMAIL_LEN: usize = 255;
+pub const OVERFLOW_PTR_SIZE: usize = size_of::<u32>();
 
-pub const ID_SIZE: usize = size_of::<i32>();
-pub const USERNAME_SIZE: usize = USERNAME_LEN;
-pub const EMAIL_SIZE: usize = EMAIL_LEN;
-
-pub const ID_OFFSET: usize = 0;
-pub const USERNAME_OFFSET: usize = ID_OFFSET + ID_SIZE;
-pub const EMAIL_OFFSET: usize = USERNAME_OFFSET + USERNAME_SIZE;
-pub const ROW_SIZE: usize = EMAIL_OFFSET + EMAIL_SIZE;
-
-pub const PAGE_SIZE: usize = 4096;
-pub const ROWS_PER_PAGE: usize = PAGE_SIZE / ROW_SIZE;
 pub const TABLE_MAX_PAGES: usize = 100;
-pub const TABLE_MAX_ROWS: usize = TABLE_MAX_PAGES * ROWS_PER_PAGE;
 
-#[derive(Debug, Clone)]
-#[repr(C)]
+/// Rows live in slotted leaf pages. A leaf starts with its cell count and
+/// the offset where cell content begins, followed by an array of `u16` cell
+/// pointers sorted by key. Cells are packed from the end of the page
+/// towards the pointer array.
+///
+/// ```text
+/// [cell count u16][content start u32][pointer 1]...[pointer n] free [cell n]...[cell 1]
+/// ```
+const LEAF_CELL_COUNT_SIZE: usize = size_of::<u16>();
+const LEAF_CONTENT_START_SIZE: usize = size_of::<u32>();
+const LEAF_HEADER_SIZE: usize = LEAF_CELL_COUNT_SIZE + LEAF_CONTENT_START_SIZE;
+const CELL_POINTER_SIZE: usize = size_of::<u16>();
+
+/// A cell is `[key varint][record len varint][record]`. Records too long to
+/// keep at least `MIN_CELLS_PER_PAGE` cells in a page keep their first
+/// bytes in the cell and end with the first page of an overflow chain
+/// holding the rest.
+const MIN_CELLS_PER_PAGE: usize = 4;
+const MAX_CELL_HEADER_SIZE: usize = 2 * 10;
+
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Row {
-    pub(crate) id: i32,
-    pub(crate) name: [u8; USERNAME_LEN],
-    pub(crate) email: [u8; EMAIL_LEN],
+    /// The rowid the row is stored under.
+    pub(crate) key: i64,
+    /// One value per column of the schema.
+    pub(crate) values: Vec<Value>,
 }
 
 // pub static TABLE: LazyLock<Mutex<Table>> = LazyLock::new(|| {
 //     Mutex::new(Table::db_open(String::from("database.db")).expect("Cant Create or Read Database!"))
 // });
 
-pub type Page = [u8; PAGE_SIZE];
-
-pub struct Table {
-    pub num_rows: usize,
-    pub pager: Pager,
+pub type Page = [u8];
+
+/// A table's root page starts with the highest rowid ever stored in it and
+/// the number of leaf pages. The `u32` page numbers of the leaves follow in
+/// key order, so rows and overflow pages can share the file.
+const ROOT_MAX_ROWID_SIZE: usize = size_of::<i64>();
+const ROOT_LEAF_COUNT_SIZE: usize = size_of::<u32>();
+const ROOT_HEADER_SIZE: usize = ROOT_MAX_ROWID_SIZE + ROOT_LEAF_COUNT_SIZE;
+const DIRECTORY_ENTRY_SIZE: usize = size_of::<u32>();
+
+/// One table of an open database.
+pub struct Table<'db> {
+    /// The highest rowid ever stored, which `AUTOINCREMENT` never goes back
+    /// below even after the row is deleted.
+    pub max_rowid: i64,
+    pub pager: &'db mut Pager,
+    pub schema: Schema,
+    pub root_page: usize,
+    /// The leaf pages, in key order. Leaves are never empty.
+    pub leaves: Vec<usize>,
 }
 
-impl Table {
-    pub fn db_open(filename: &String) -> io::Result<Self> {
-        let mut file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(&filename)?;
-
-        // lseek(fd, 0, SEEK_END)
-        let file_length = file.seek(SeekFrom::End(0))?;
-
-        Ok(Self {
-            num_rows: file_length as usize / ROW_SIZE,
-            pager: Pager {
-                file,
-                content_length: file_length as usize,
-                pages: std::array::from_fn(|_| None),
-            },
-        })
-    }
-
-    pub fn db_close(&mut self) -> io::Result<()> {
-        let num_full_pages = self.pager.content_length / PAGE_SIZE;
-        for i in 0..num_full_pages {
-            if self.pager.pages[i].is_some() {
-                self.pager.flush(i, PAGE_SIZE)?;
-            }
+impl<'db> Table<'db> {
+    /// Opens the table rooted at `root_page`.
+    pub fn open(pager: &'db mut Pager, schema: Schema, root_page: usize) -> Result<Self> {
+        let mut table = Self {
+            max_rowid: 0,
+            pager,
+            schema,
+            root_page,
+            leaves: Vec::new(),
+        };
+        let root = table.read_page(root_page)?;
+        let max_rowid = i64::from_le_bytes(root[..ROOT_MAX_ROWID_SIZE].try_into().unwrap());
+        let leaf_count = read_u32(&root[ROOT_MAX_ROWID_SIZE..]) as usize;
+        let entries = root[ROOT_HEADER_SIZE..]
+            .chunks_exact(DIRECTORY_ENTRY_SIZE)
+            .take(leaf_count);
+        if entries.len() < leaf_count {
+            return Err(Error::corrupt(format!("root page {root_page} lists more leaves than it holds")));
         }
+        let leaves = entries.map(|entry| read_u32(entry) as usize).collect();
+        table.max_rowid = max_rowid;
+        table.leaves = leaves;
+        Ok(table)
+    }
 
-        let remainder = self.pager.content_length % PAGE_SIZE;
-        if remainder != 0 {
-            let last = num_full_pages;
-            if self.pager.pages[last].is_some() {
-                self.pager.flush(last, remainder)?;
-            }
+    /// Writes the highest rowid and the leaf directory back to the root page.
+    fn write_root(&mut self) -> Result<()> {
+        if ROOT_HEADER_SIZE + self.leaves.len() * DIRECTORY_ENTRY_SIZE > self.pager.page_size() {
+            return Err(Limit::TableFull.into());
+        }
+        let (max_rowid, leaves) = (self.max_rowid, self.leaves.clone());
+        let root_page = self.root_page;
+        let root = self.get_page(root_page)?;
+        root[..ROOT_MAX_ROWID_SIZE].copy_from_slice(&max_rowid.to_le_bytes());
+        root[ROOT_MAX_ROWID_SIZE..ROOT_HEADER_SIZE].copy_from_slice(&(leaves.len() as u32).to_le_bytes());
+        for (entry, leaf) in root[ROOT_HEADER_SIZE..].chunks_exact_mut(DIRECTORY_ENTRY_SIZE).zip(leaves) {
+            entry.copy_from_slice(&(leaf as u32).to_le_bytes());
         }
-
         Ok(())
     }
-    pub fn get_page_mut(&mut self, page_num: usize) -> &mut Page {
-        self.get_page(page_num).expect("get_page failed")
+
+    /// The rowid for a row inserted without one: one past the highest rowid
+    /// in the table, or with `AUTOINCREMENT` one past the highest ever used.
+    /// The first row gets 1.
+    pub fn next_rowid(&mut self) -> Result<i64> {
+        let last = match self.leaves.last() {
+            Some(&page_num) => {
+                let count = self.cell_count(page_num)?;
+                let cell = self.leaf_cell(page_num, count.saturating_sub(1))?;
+                cell.map_or(0, |cell| cell_key(&cell))
+            }
+            None => 0,
+        };
+        let highest = match self.schema.autoincrement {
+            true => last.max(self.max_rowid),
+            false => last,
+        };
+        highest.max(0).checked_add(1).ok_or(Error::Limit(Limit::RowidExhausted))
     }
 
+    /// The longest record kept entirely in its cell.
+    fn max_local(&self) -> usize {
+        (self.pager.page_size() - LEAF_HEADER_SIZE) / MIN_CELLS_PER_PAGE
+            - CELL_POINTER_SIZE
+            - MAX_CELL_HEADER_SIZE
+            - OVERFLOW_PTR_SIZE
+    }
 
+    /// Copies the cells of leaf `page_num` out of the page, in key order.
+    fn leaf_cells(&mut self, page_num: usize) -> Result<Vec<Vec<u8>>> {
+        let count = self.cell_count(page_num)?;
+        (0..count)
+            .map(|index| self.leaf_cell(page_num, index))
+            .collect::<Result<Option<Vec<_>>>>()?
+            .ok_or_else(|| malformed_leaf(page_num))
+    }
 
-    pub fn get_page(&mut self, page_num: usize) -> Result<&mut Page, SelectError> {
-        if page_num >= TABLE_MAX_PAGES {
-            return Err(SelectError::OutOfBounds);
+    fn cell_count(&mut self, page_num: usize) -> Result<usize> {
+        let page = self.read_page(page_num)?;
+        Ok(u16::from_le_bytes(page[..LEAF_CELL_COUNT_SIZE].try_into().unwrap()) as usize)
+    }
+
+    /// Copies the `index`-th cell of leaf `page_num` out of the page, or
+    /// `None` past the last cell.
+    fn leaf_cell(&mut self, page_num: usize, index: usize) -> Result<Option<Vec<u8>>> {
+        if index >= self.cell_count(page_num)? {
+            return Ok(None);
         }
+        let max_local = self.max_local();
+        let page = self.read_page(page_num)?;
+        let cell = cell_at(page, index).ok_or_else(|| malformed_leaf(page_num))?;
+        let len = cell_len(cell, max_local).ok_or_else(|| malformed_leaf(page_num))?;
+        Ok(Some(cell.get(..len).ok_or_else(|| malformed_leaf(page_num))?.to_vec()))
+    }
 
-        if self.pager.pages[page_num].is_none() {
-            let mut page = [0u8; PAGE_SIZE];
+    /// The key of the `index`-th cell of leaf `page_num`, read in place.
+    fn key_at(&mut self, page_num: usize, index: usize) -> Result<i64> {
+        let page = self.read_page(page_num)?;
+        cell_at(page, index)
+            .and_then(record::read_varint)
+            .map(|(key, _)| key as i64)
+            .ok_or_else(|| malformed_leaf(page_num))
+    }
+
+    /// Lays `cells` out in leaf `page_num`, which they must fit in.
+    fn write_leaf(&mut self, page_num: usize, cells: &[Vec<u8>]) -> Result<()> {
+        let page = self.get_page(page_num)?;
+        page.fill(0);
+        let mut content_start = page.len();
+        for (i, cell) in cells.iter().enumerate() {
+            content_start -= cell.len();
+            page[content_start..content_start + cell.len()].copy_from_slice(cell);
+            let pointer = LEAF_HEADER_SIZE + i * CELL_POINTER_SIZE;
+            page[pointer..pointer + CELL_POINTER_SIZE].copy_from_slice(&(content_start as u16).to_le_bytes());
+        }
+        page[..LEAF_CELL_COUNT_SIZE].copy_from_slice(&(cells.len() as u16).to_le_bytes());
+        page[LEAF_CELL_COUNT_SIZE..LEAF_HEADER_SIZE].copy_from_slice(&(content_start as u32).to_le_bytes());
+        Ok(())
+    }
 
-            let mut num_pages = self.pager.content_length / PAGE_SIZE;
-            if self.pager.content_length % PAGE_SIZE != 0 {
-                num_pages += 1;
+    /// Replaces the cells of the `leaf`-th leaf. Cells that no longer fit
+    /// move to new leaves right after it, and a leaf left empty is dropped
+    /// from the directory and freed.
+    fn store_leaf(&mut self, leaf: usize, cells: Vec<Vec<u8>>) -> Result<()> {
+        let mut pages: Vec<Vec<Vec<u8>>> = vec![vec![]];
+        let mut used = LEAF_HEADER_SIZE;
+        for cell in cells {
+            let size = cell.len() + CELL_POINTER_SIZE;
+            if used + size > self.pager.page_size() {
+                pages.push(vec![]);
+                used = LEAF_HEADER_SIZE;
             }
+            used += size;
+            pages.last_mut().unwrap().push(cell);
+        }
 
-            if page_num < num_pages {
-                let offset = (page_num * PAGE_SIZE) as u64;
-                self.pager.file.seek(SeekFrom::Start(offset)).unwrap();
-
-                let bytes_to_read =
-                    if page_num == num_pages - 1 && (self.pager.content_length % PAGE_SIZE != 0) {
-                        self.pager.content_length % PAGE_SIZE
-                    } else {
-                        PAGE_SIZE
-                    };
-
-                self.pager
-                    .file
-                    .read_exact(&mut page[..bytes_to_read])
-                    .unwrap();
+        if pages[0].is_empty() {
+            let page_num = self.leaves.remove(leaf);
+            self.pager.free_page(page_num);
+            return self.write_root();
+        }
+        for (i, cells) in pages.iter().enumerate() {
+            let page_num = match i {
+                0 => self.leaves[leaf],
+                _ => {
+                    let page_num = self.pager.allocate_page().ok_or(Limit::TableFull)?;
+                    self.leaves.insert(leaf + i, page_num);
+                    page_num
+                }
+            };
+            self.write_leaf(page_num, cells)?;
+        }
+        if pages.len() > 1 {
+            self.write_root()?;
+        }
+        Ok(())
+    }
+
+    /// Every page the table takes up: its root, its leaves and the overflow
+    /// chains of their cells.
+    pub fn pages(&mut self) -> Result<Vec<usize>> {
+        let max_local = self.max_local();
+        let mut pages = vec![self.root_page];
+        for page_num in self.leaves.clone() {
+            pages.push(page_num);
+            for cell in self.leaf_cells(page_num)? {
+                if let Some(overflow) = cell_overflow(&cell, max_local) {
+                    pages.extend(overflow::chain_pages(self, overflow)?);
+                }
             }
+        }
+        Ok(pages)
+    }
+
+    /// Finds where `key` is or would go: the index of a leaf and of a cell
+    /// in it. `None` if the table has no leaves yet.
+    pub(crate) fn seek(&mut self, key: i64) -> Result<Option<(usize, usize)>> {
+        let Some(last) = self.leaves.len().checked_sub(1) else {
+            return Ok(None);
+        };
+        // The first leaf whose last key isn't below `key`, or the last leaf.
+        let leaf = partition_point(last, |leaf| {
+            let page_num = self.leaves[leaf];
+            let count = self.cell_count(page_num)?;
+            let end = count.checked_sub(1).ok_or_else(|| malformed_leaf(page_num))?;
+            Ok(self.key_at(page_num, end)? < key)
+        })?;
+        let page_num = self.leaves[leaf];
+        let count = self.cell_count(page_num)?;
+        let position = partition_point(count, |index| Ok(self.key_at(page_num, index)? < key))?;
+        Ok(Some((leaf, position)))
+    }
 
-            self.pager.pages[page_num] = Some(Box::from(page));
+    /// Read-only access to a page. Pages already in the cache win, since they
+    /// may hold unflushed writes; otherwise the page is served from the mmap
+    /// when it is covered by the mapping, and read into the cache if not.
+    pub fn read_page(&mut self, page_num: usize) -> Result<&[u8]> {
+        // Pages past the limit are turned away by `load_page`.
+        if page_num < TABLE_MAX_PAGES
+            && self.pager.pages[page_num].is_none()
+            && self.pager.mapped_page(page_num).is_some()
+        {
+            return Ok(self.pager.mapped_page(page_num).unwrap());
         }
 
+        self.load_page(page_num)?;
+        Ok(self.pager.pages[page_num].as_deref().unwrap())
+    }
+
+    /// Write access to a page, which marks it to be flushed on commit.
+    pub fn get_page(&mut self, page_num: usize) -> Result<&mut Page> {
+        self.load_page(page_num)?;
+        self.pager.journal_page(page_num);
+        self.pager.dirty[page_num] = true;
         Ok(self.pager.pages[page_num].as_mut().unwrap())
     }
+
+    /// Reads a page into the cache if it isn't there yet.
+    fn load_page(&mut self, page_num: usize) -> Result<()> {
+        if page_num >= TABLE_MAX_PAGES {
+            return Err(Error::corrupt(format!("page {page_num} is past the last page a file may have")));
+        }
+
+        if self.pager.pages[page_num].is_none() {
+            let page_size = self.pager.page_size();
+            let mut page = vec![0u8; page_size];
+
+            self.pager
+                .read_page_into(page_num, &mut page)
+                .map_err(Error::io(format!("read page {page_num}")))?;
+
+            self.pager.pages[page_num] = Some(page.into_boxed_slice());
+        }
+        Ok(())
+    }
 }
 
-pub fn insert_row(cur: &mut Cursor, id: i32, name: &str, email: &str) {
-    cur.row_num = id as usize;
-    let page_num = cur.row_num / ROWS_PER_PAGE;
-    let byte_offset = cur.byte_offset();
+/// Stores `values`, one per column of the schema, under `key`, replacing
+/// the row already stored there.
+pub fn insert_row(cur: &mut Cursor, key: i64, values: &[Value]) -> Result<()> {
+    let table = &mut *cur.table;
+
+    let (leaf, position) = match table.seek(key)? {
+        Some(found) => found,
+        None => {
+            let page_num = table.pager.allocate_page().ok_or(Limit::TableFull)?;
+            table.leaves.push(page_num);
+            (0, 0)
+        }
+    };
+    let mut cells = table.leaf_cells(table.leaves[leaf])?;
+    let replaces = cells.get(position).is_some_and(|cell| cell_key(cell) == key);
+
+    // A replaced row's overflow chain is reused for the new record.
+    let max_local = table.max_local();
+    let old_overflow = match replaces {
+        true => cell_overflow(&cells[position], max_local).unwrap_or(0),
+        false => 0,
+    };
+    let record = record::encode(values);
+    let mut cell = Vec::with_capacity(MAX_CELL_HEADER_SIZE + record.len().min(max_local) + OVERFLOW_PTR_SIZE);
+    record::write_varint(&mut cell, key as u64);
+    record::write_varint(&mut cell, record.len() as u64);
+    if record.len() <= max_local {
+        cell.extend_from_slice(&record);
+        free_chain(table, old_overflow)?;
+    } else {
+        let (local, spill) = record.split_at(max_local);
+        let overflow = overflow::write_chain(table, spill, old_overflow)?;
+        cell.extend_from_slice(local);
+        cell.extend_from_slice(&overflow.to_le_bytes());
+    }
 
-    {
-        let page: &mut Page = cur.value();
+    if replaces {
+        cells[position] = cell;
+    } else {
+        cells.insert(position, cell);
+    }
+    table.store_leaf(leaf, cells)?;
 
-        page[byte_offset + ID_OFFSET..byte_offset + ID_OFFSET + ID_SIZE]
-            .copy_from_slice(&id.to_le_bytes());
+    table.max_rowid = table.max_rowid.max(key);
+    table.write_root()
+}
 
-        let name_byte = to_fixed_32_truncate(name);
-        page[byte_offset + USERNAME_OFFSET..byte_offset + USERNAME_OFFSET + USERNAME_SIZE]
-            .copy_from_slice(&name_byte);
+/// Reads row `key`, failing with `Error::NotFound` if there is none.
+pub fn fetch_row(cur: &mut Cursor, key: i64) -> Result<Row> {
+    let not_found = || Error::not_found(Object::Row, key.to_string());
+    let Some((leaf, cell)) = cur.table.seek(key)? else {
+        return Err(not_found());
+    };
+    cur.leaf = leaf;
+    cur.cell = cell;
+    cursor_row(cur)?.filter(|row| row.key == key).ok_or_else(not_found)
+}
 
-        let email_byte = to_fixed_255_truncate(email);
-        page[byte_offset + EMAIL_OFFSET..byte_offset + EMAIL_OFFSET + EMAIL_SIZE]
-            .copy_from_slice(&email_byte);
+/// Removes row `key`, if there is one, freeing the overflow pages it used.
+pub fn delete_row(cur: &mut Cursor, key: i64) -> Result<()> {
+    let Some((leaf, position)) = cur.table.seek(key)? else {
+        return Ok(());
+    };
+    let mut cells = cur.table.leaf_cells(cur.table.leaves[leaf])?;
+    if cells.get(position).is_some_and(|cell| cell_key(cell) == key) {
+        let cell = cells.remove(position);
+        let overflow = cell_overflow(&cell, cur.table.max_local()).unwrap_or(0);
+        free_chain(cur.table, overflow)?;
+        cur.table.store_leaf(leaf, cells)?;
     }
+    Ok(())
+}
 
-    let end_of_row = page_num * PAGE_SIZE + byte_offset + ROW_SIZE;
-    cur.table.pager.content_length = cur.table.pager.content_length.max(end_of_row);
-    cur.table.num_rows = cur.table.num_rows.max(cur.row_num + 1);
+/// Reads the row under the cursor, moving on to the next leaf when the
+/// cursor is past the end of its own. `None` at the end of the table.
+pub fn cursor_row(cur: &mut Cursor) -> Result<Option<Row>> {
+    let max_local = cur.table.max_local();
+    let cell = loop {
+        let Some(&page_num) = cur.table.leaves.get(cur.leaf) else {
+            cur.end_of_table = true;
+            return Ok(None);
+        };
+        if let Some(cell) = cur.table.leaf_cell(page_num, cur.cell)? {
+            break cell;
+        }
+        cur.leaf += 1;
+        cur.cell = 0;
+    };
+
+    let malformed = || Error::corrupt("malformed cell");
+    let (key, key_len) = record::read_varint(&cell).ok_or_else(malformed)?;
+    let (len, len_len) = record::read_varint(&cell[key_len..]).ok_or_else(malformed)?;
+    let len = len as usize;
+    let local = &cell[key_len + len_len..];
+    let record = if len <= max_local {
+        local[..len].to_vec()
+    } else {
+        let overflow = cell_overflow(&cell, max_local).ok_or_else(malformed)?;
+        let mut record = local[..max_local].to_vec();
+        record.extend(overflow::read_chain(cur.table, overflow, len - max_local)?);
+        record
+    };
+
+    // Records written before a column was added are short of its values,
+    // which read as the column's default.
+    let mut values = record::decode(&record)?;
+    let columns = &cur.table.schema.columns;
+    if let Some(added) = columns.get(values.len()..) {
+        values.extend(added.iter().map(|column| column.default_value()));
+    }
+    Ok(Some(Row {
+        key: key as i64,
+        values,
+    }))
 }
 
-pub fn to_fixed_32_truncate(s: &str) -> [u8; 32] {
-    let bytes = s.as_bytes();
-    let mut out = [0u8; 32];
-    let n = bytes.len().min(32);
-    out[..n].copy_from_slice(&bytes[..n]);
-    out
+/// Hands the overflow chain starting at `first_page` back to the freelist.
+fn free_chain(table: &mut Table, first_page: u32) -> Result<()> {
+    for page_num in overflow::chain_pages(table, first_page)? {
+        table.pager.free_page(page_num);
+    }
+    Ok(())
 }
 
-pub fn to_fixed_255_truncate(s: &str) -> [u8; 255] {
-    let bytes = s.as_bytes();
-    let mut out = [0u8; 255];
-    let n = bytes.len().min(255);
-    out[..n].copy_from_slice(&bytes[..n]);
-    out
+/// The number of indexes in `0..len` that `below` holds for, which must be
+/// all those before the first it doesn't hold for: a binary search.
+fn partition_point(len: usize, mut below: impl FnMut(usize) -> Result<bool>) -> Result<usize> {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match below(mid)? {
+            true => low = mid + 1,
+            false => high = mid,
+        }
+    }
+    Ok(low)
 }
 
-pub fn fetch_row(cur: &mut Cursor, id: i32) -> Result<Row, std::io::Error> {
-    cur.row_num = id as usize;
-    let page_num = cur.row_num / ROWS_PER_PAGE;
-    let byte_offset = cur.byte_offset();
+/// The bytes of a leaf from the start of its `index`-th cell on, `None` if
+/// the pointer to it is malformed.
+fn cell_at(page: &Page, index: usize) -> Option<&[u8]> {
+    let pointer = LEAF_HEADER_SIZE + index * CELL_POINTER_SIZE;
+    let start = u16::from_le_bytes(page.get(pointer..pointer + CELL_POINTER_SIZE)?.try_into().unwrap()) as usize;
+    page.get(start..)
+}
 
-    let page: &mut [u8; PAGE_SIZE] = cur.table.get_page_mut(page_num);
+/// The key a cell starts with.
+fn cell_key(cell: &[u8]) -> i64 {
+    record::read_varint(cell).map_or(0, |(key, _)| key as i64)
+}
 
-    let name_byte: &[u8] =
-        &page[byte_offset + USERNAME_OFFSET..byte_offset + USERNAME_OFFSET + USERNAME_SIZE];
-    let email_byte: &[u8] =
-        &page[byte_offset + EMAIL_OFFSET..byte_offset + EMAIL_OFFSET + EMAIL_SIZE];
+/// The length of the cell at the start of `bytes`, `None` if it is malformed.
+fn cell_len(bytes: &[u8], max_local: usize) -> Option<usize> {
+    let (_, key_len) = record::read_varint(bytes)?;
+    let (len, len_len) = record::read_varint(&bytes[key_len..])?;
+    let len = len as usize;
+    let payload = if len <= max_local { len } else { max_local + OVERFLOW_PTR_SIZE };
+    Some(key_len + len_len + payload)
+}
 
-    Ok(Row {
-        id,
-        name: slice_to_32(name_byte).unwrap(),
-        email: slice_to_255(email_byte).unwrap(),
-    })
+/// The first overflow page of a cell whose record spilled.
+fn cell_overflow(cell: &[u8], max_local: usize) -> Option<u32> {
+    let (_, key_len) = record::read_varint(cell)?;
+    let (len, _) = record::read_varint(&cell[key_len..])?;
+    (len as usize > max_local).then(|| read_u32(&cell[cell.len() - OVERFLOW_PTR_SIZE..]))
 }
-fn slice_to_32(b: &[u8]) -> Result<[u8; 32], &'static str> {
-    b.try_into().map_err(|_| "expected 32 bytes")
+
+fn malformed_leaf(page_num: usize) -> Error {
+    Error::corrupt(format!("leaf page {page_num} is malformed"))
 }
 
-fn slice_to_255(b: &[u8]) -> Result<[u8; 255], &'static str> {
-    b.try_into().map_err(|_| "expected 255 bytes")
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
 }
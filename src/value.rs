@@ -0,0 +1,256 @@
+use crate::ast::Literal;
+use crate::schema::ColumnType;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A value produced while evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// SQL's NULL: no value, or an unknown one.
+    Null,
+    /// A signed 64-bit integer.
+    Integer(i64),
+    /// A 64-bit floating-point number.
+    Real(f64),
+    /// A string of UTF-8 text.
+    Text(String),
+    /// Bytes stored as they are.
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    /// Whether this is NULL.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// What kind of value this is, as error messages name it.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "NULL",
+            Value::Integer(_) => "an integer",
+            Value::Real(_) => "a real",
+            Value::Text(_) => "text",
+            Value::Blob(_) => "a blob",
+        }
+    }
+
+    /// The value as a truth value, `None` standing for SQL's unknown.
+    pub(crate) fn truth(&self) -> Option<bool> {
+        match self.numeric() {
+            Value::Integer(n) => Some(n != 0),
+            Value::Real(n) => Some(n != 0.0),
+            _ => None,
+        }
+    }
+
+    /// Converts text to the number it starts with (0 if none), as arithmetic does.
+    pub(crate) fn numeric(&self) -> Value {
+        match self {
+            Value::Text(text) => parse_numeric_prefix(text),
+            Value::Blob(bytes) => parse_numeric_prefix(&String::from_utf8_lossy(bytes)),
+            other => other.clone(),
+        }
+    }
+
+    /// Converts the value for a column with the given affinity. Text that
+    /// spells out a number becomes that number in numeric columns, numbers
+    /// become text in text columns, and reals without a fraction are stored
+    /// as integers where integers are preferred. NULL and blobs never change.
+    pub(crate) fn with_affinity(self, affinity: ColumnType) -> Value {
+        match (affinity, self) {
+            (_, value @ (Value::Null | Value::Blob(_))) | (ColumnType::Blob, value) => value,
+            (ColumnType::Text, Value::Text(text)) => Value::Text(text),
+            (ColumnType::Text, number) => Value::Text(number.to_string()),
+            (ColumnType::Real, Value::Integer(n)) => Value::Real(n as f64),
+            (ColumnType::Real, Value::Text(text)) => match parse_number(&text) {
+                Some(Value::Integer(n)) => Value::Real(n as f64),
+                Some(number) => number,
+                None => Value::Text(text),
+            },
+            (ColumnType::Real, real) => real,
+            (ColumnType::Integer | ColumnType::Numeric, Value::Text(text)) => match parse_number(&text) {
+                Some(number) => number.with_affinity(affinity),
+                None => Value::Text(text),
+            },
+            (ColumnType::Integer | ColumnType::Numeric, Value::Real(n)) => match exact_integer(n) {
+                Some(n) => Value::Integer(n),
+                None => Value::Real(n),
+            },
+            (ColumnType::Integer | ColumnType::Numeric, integer) => integer,
+        }
+    }
+
+    /// Orders values the way SQL comparisons do: numbers before text before
+    /// blobs, integers and reals compared by value. `None` if either side is NULL.
+    pub(crate) fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => None,
+            (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+            (Value::Integer(a), Value::Real(b)) => (*a as f64).partial_cmp(b),
+            (Value::Real(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
+            (Value::Real(a), Value::Real(b)) => a.partial_cmp(b),
+            (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+            (Value::Blob(a), Value::Blob(b)) => Some(a.cmp(b)),
+            (Value::Blob(_), _) => Some(Ordering::Greater),
+            (_, Value::Blob(_)) => Some(Ordering::Less),
+            (Value::Text(_), _) => Some(Ordering::Greater),
+            (_, Value::Text(_)) => Some(Ordering::Less),
+        }
+    }
+}
+
+/// A type a value of a row can be read as, through
+/// [`Row::get`](crate::Row::get).
+pub trait FromValue: Sized {
+    /// What the type holds, as error messages name it.
+    const KIND: &'static str;
+
+    /// Converts `value`, or returns `None` if it holds something else.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl FromValue for Value {
+    const KIND: &'static str = "a value";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl FromValue for i64 {
+    const KIND: &'static str = "an integer";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Integers are widened to reals.
+impl FromValue for f64 {
+    const KIND: &'static str = "a real";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(n) => Some(*n as f64),
+            Value::Real(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Any integer other than 0 is true, as in a WHERE clause.
+impl FromValue for bool {
+    const KIND: &'static str = "a boolean";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        i64::from_value(value).map(|n| n != 0)
+    }
+}
+
+impl FromValue for String {
+    const KIND: &'static str = "text";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Text(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for Vec<u8> {
+    const KIND: &'static str = "a blob";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Blob(bytes) => Some(bytes.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// NULL reads as `None`.
+impl<T: FromValue> FromValue for Option<T> {
+    const KIND: &'static str = T::KIND;
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl From<&Value> for Literal {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => Literal::Null,
+            Value::Integer(n) => Literal::Integer(*n),
+            Value::Real(n) => Literal::Real(*n),
+            Value::Text(s) => Literal::String(s.clone()),
+            Value::Blob(bytes) => Literal::Blob(bytes.clone()),
+        }
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Null => Value::Null,
+            Literal::Integer(n) => Value::Integer(*n),
+            Literal::Real(n) => Value::Real(*n),
+            Literal::String(s) => Value::Text(s.clone()),
+            Literal::Blob(bytes) => Value::Blob(bytes.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Integer(n) => write!(f, "{n}"),
+            Value::Real(n) => write!(f, "{n:?}"),
+            Value::Text(s) => write!(f, "{s}"),
+            Value::Blob(bytes) => write!(f, "{}", String::from_utf8_lossy(bytes)),
+        }
+    }
+}
+
+fn parse_numeric_prefix(text: &str) -> Value {
+    let text = text.trim_start();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+        .unwrap_or(text.len());
+
+    // Back off until what's left is a number, e.g. `12e` or `3-4` down to `12` and `3`.
+    let mut prefix = &text[..end];
+    while !prefix.is_empty() && prefix.parse::<f64>().is_err() {
+        prefix = &prefix[..prefix.len() - 1];
+    }
+    if let Ok(n) = prefix.parse::<i64>() {
+        return Value::Integer(n);
+    }
+    prefix.parse().map(Value::Real).unwrap_or(Value::Integer(0))
+}
+
+/// The number `text` spells out in full, leading and trailing spaces aside.
+fn parse_number(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit() || "+-.eE".contains(c)) {
+        return None;
+    }
+    if let Ok(n) = text.parse::<i64>() {
+        return Some(Value::Integer(n));
+    }
+    text.parse().ok().map(Value::Real)
+}
+
+/// `n` as an integer, if it has no fraction and is in range.
+fn exact_integer(n: f64) -> Option<i64> {
+    (n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64).then_some(n as i64)
+}
@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::ast::Statement;
+    use crate::cursor::Cursor;
+    use crate::database::Database;
+    use crate::parser::parse;
+    use crate::schema::ColumnType;
+    use crate::statement::insert;
+    use crate::table::fetch_row;
+    use crate::value::Value;
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    #[test]
+    fn affinity_converts_only_what_survives_the_trip() {
+        use ColumnType::*;
+        assert_eq!(text("42").with_affinity(Integer), Value::Integer(42));
+        assert_eq!(text(" 4.0 ").with_affinity(Numeric), Value::Integer(4));
+        assert_eq!(text("1e3").with_affinity(Real), Value::Real(1000.0));
+        assert_eq!(text("4.5").with_affinity(Integer), Value::Real(4.5));
+        assert_eq!(text("12 apples").with_affinity(Integer), text("12 apples"));
+        assert_eq!(text("inf").with_affinity(Real), text("inf"));
+        assert_eq!(Value::Real(3.0).with_affinity(Integer), Value::Integer(3));
+        assert_eq!(Value::Integer(3).with_affinity(Real), Value::Real(3.0));
+        assert_eq!(Value::Real(2.5).with_affinity(Text), text("2.5"));
+        assert_eq!(Value::Integer(7).with_affinity(Blob), Value::Integer(7));
+        assert_eq!(text("7").with_affinity(Blob), text("7"));
+        assert_eq!(Value::Blob(vec![1]).with_affinity(Text), Value::Blob(vec![1]));
+        assert_eq!(Value::Null.with_affinity(Integer), Value::Null);
+    }
+
+    #[test]
+    fn typed_values_round_trip_through_storage() {
+        let test_database_name = String::from("test_db_affinity");
+        let mut db = Database::open(&test_database_name).unwrap();
+        let Ok(Statement::CreateTable(create)) =
+            parse("CREATE TABLE t (id INTEGER PRIMARY KEY, n INT, r REAL, s TEXT, b BLOB, any_)")
+        else {
+            panic!("expected a create table");
+        };
+        db.create_table(&create).unwrap();
+
+        let Ok(Statement::Insert(statement)) = parse(
+            "INSERT INTO t VALUES (1, '-12', 5, 3.25, X'00fF10', 9223372036854775807), \
+             (2, NULL, -0.5, NULL, 'text', x''), (3, 'n/a', 1e300, 10, NULL, 'é')",
+        ) else {
+            panic!("expected an insert");
+        };
+        let mut table = db.table("t").unwrap();
+        insert(&mut Cursor::new(&mut table), &statement).unwrap();
+        db.commit().unwrap();
+        drop(db);
+
+        let mut db = Database::open(&test_database_name).unwrap();
+        let mut table = db.table("t").unwrap();
+        let mut cur = Cursor::new(&mut table);
+        assert_eq!(
+            fetch_row(&mut cur, 1).unwrap().values,
+            [
+                Value::Integer(1),
+                Value::Integer(-12),
+                Value::Real(5.0),
+                text("3.25"),
+                Value::Blob(vec![0, 255, 16]),
+                Value::Integer(i64::MAX),
+            ]
+        );
+        assert_eq!(
+            fetch_row(&mut cur, 2).unwrap().values,
+            [Value::Integer(2), Value::Null, Value::Real(-0.5), Value::Null, text("text"), Value::Blob(vec![])]
+        );
+        assert_eq!(
+            fetch_row(&mut cur, 3).unwrap().values,
+            [Value::Integer(3), text("n/a"), Value::Real(1e300), text("10"), Value::Null, text("é")]
+        );
+
+        fs::remove_file(&test_database_name).unwrap();
+    }
+
+    #[test]
+    fn blob_literals_need_an_even_number_of_hex_digits() {
+        assert!(parse("SELECT * FROM t WHERE b = X'abc'").is_err());
+        assert!(parse("SELECT * FROM t WHERE b = X'zz'").is_err());
+        let Ok(Statement::Select(select)) = parse("SELECT * FROM t WHERE b = x'0A'") else {
+            panic!("expected a select");
+        };
+        assert_eq!(select.where_clause.unwrap().to_string(), "b = X'0A'");
+    }
+}